@@ -14,7 +14,7 @@ use std::time::{Duration, Instant};
 use tracing::{debug, error, warn};
 use webrtc_vad::{SampleRate, Vad, VadMode};
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
-use crate::media_event::Outgoing;
+use crate::media_event::{Outgoing, WsOutbound};
 
 /// Reason why a segment was closed
 #[derive(Debug, Clone, PartialEq)]
@@ -912,26 +912,26 @@ pub async fn send_turn_to_gemini(
     const MAX_WEBSOCKET: usize = 1_000_000; // 1 MiB
     const FIRST_MAX: usize = 256_000; // 0.25 MiB
 
-    // First slice - set activity_start=true
-    let first_chunk_size = std::cmp::min(FIRST_MAX, pcm_bytes.len());
+    // Activity markers go in their own frames around the audio
     gemini_client
-        .send_audio_with_activity(&pcm_bytes[..first_chunk_size], true, false, false)
+        .send_outbound(WsOutbound::ActivityStart)
         .await
         .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?;
 
-    // Middle slices (if any)
-    if pcm_bytes.len() > first_chunk_size {
-        for chunk in pcm_bytes[first_chunk_size..].chunks(MAX_WEBSOCKET) {
-            gemini_client
-                .send_audio_with_activity(chunk, false, false, false)
-                .await
-                .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?;
-        }
+    // First slice is kept small so the model starts listening early
+    let first_chunk_size = std::cmp::min(FIRST_MAX, pcm_bytes.len());
+    let chunks = std::iter::once(&pcm_bytes[..first_chunk_size])
+        .chain(pcm_bytes[first_chunk_size..].chunks(MAX_WEBSOCKET))
+        .filter(|chunk| !chunk.is_empty());
+    for chunk in chunks {
+        gemini_client
+            .send_outbound(WsOutbound::Audio(chunk.to_vec()))
+            .await
+            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?;
     }
 
-    // Final slice - set activity_end=true
     gemini_client
-        .send_audio_with_activity(&[], false, false, true)
+        .send_outbound(WsOutbound::ActivityEnd)
         .await
         .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?;
 
@@ -0,0 +1,343 @@
+//! Credentials for connecting to the Live API
+//!
+//! An [`AuthProvider`] hands out a [`Credential`] for every new connection,
+//! which the client attaches to the WebSocket handshake as a header. Tokens
+//! that expire are cached and fetched again shortly before they run out, so
+//! reconnects always present a valid credential.
+//!
+//! Nothing here puts secrets into URLs; [`redact_url`] masks them anyway for
+//! URLs supplied by the user.
+
+use anyhow::{bail, Context, Result};
+use futures_util::future::BoxFuture;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tracing::{debug, info};
+
+/// Live API endpoint for API keys and OAuth bearer tokens
+pub const LIVE_API_URL: &str =
+    "wss://generativelanguage.googleapis.com/ws/google.ai.generativelanguage.v1beta.GenerativeService.BidiGenerateContent";
+
+/// Live API endpoint that accepts ephemeral tokens
+pub const LIVE_API_CONSTRAINED_URL: &str =
+    "wss://generativelanguage.googleapis.com/ws/google.ai.generativelanguage.v1alpha.GenerativeService.BidiGenerateContentConstrained";
+
+/// Ephemeral tokens may start new sessions for 60 seconds unless told otherwise
+const DEFAULT_EPHEMERAL_LIFETIME: Duration = Duration::from_secs(60);
+
+/// Fetch a new token when the cached one has less than this left
+const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(10);
+
+/// Query parameters whose values are secrets
+const SECRET_PARAMS: &[&str] = &["key", "access_token", "token"];
+
+/// A credential for a single connection attempt
+#[derive(Clone)]
+pub enum Credential {
+    /// Gemini API key, sent as `x-goog-api-key`
+    ApiKey(String),
+    /// Ephemeral token, sent as `Authorization: Token ...`
+    EphemeralToken(String),
+    /// OAuth access token, sent as `Authorization: Bearer ...`
+    Bearer(String),
+}
+
+impl Credential {
+    /// Endpoint to use when the configuration does not name one
+    pub fn default_url(&self) -> &'static str {
+        match self {
+            Credential::EphemeralToken(_) => LIVE_API_CONSTRAINED_URL,
+            Credential::ApiKey(_) | Credential::Bearer(_) => LIVE_API_URL,
+        }
+    }
+
+    /// Attach the credential to the WebSocket handshake request
+    pub fn apply(&self, request: &mut Request) -> Result<()> {
+        let (name, value) = match self {
+            Credential::ApiKey(key) => ("x-goog-api-key", key.clone()),
+            Credential::EphemeralToken(token) => ("authorization", format!("Token {}", token)),
+            Credential::Bearer(token) => ("authorization", format!("Bearer {}", token)),
+        };
+        let mut value = HeaderValue::from_str(&value).context("Credential is not a valid header value")?;
+        value.set_sensitive(true);
+        request.headers_mut().insert(name, value);
+        Ok(())
+    }
+}
+
+// Never print the secret itself
+impl std::fmt::Debug for Credential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            Credential::ApiKey(_) => "ApiKey",
+            Credential::EphemeralToken(_) => "EphemeralToken",
+            Credential::Bearer(_) => "Bearer",
+        };
+        write!(f, "{}(<redacted>)", kind)
+    }
+}
+
+/// Source of credentials for new connections
+pub trait AuthProvider: Send + Sync {
+    /// A credential that is valid right now, refreshed if needed
+    fn credential(&self) -> BoxFuture<'_, Result<Credential>>;
+}
+
+/// A fixed Gemini API key
+pub struct ApiKey(pub String);
+
+impl AuthProvider for ApiKey {
+    fn credential(&self) -> BoxFuture<'_, Result<Credential>> {
+        Box::pin(async move { Ok(Credential::ApiKey(self.0.clone())) })
+    }
+}
+
+/// Ephemeral tokens minted by a command, typically a call to a backend that
+/// holds the real API key.
+///
+/// The command prints either the bare token or the `AuthToken` JSON
+/// (`{"name": "...", "expireTime": "..."}`). Without an `expireTime` the token
+/// is assumed to start new sessions for 60 seconds.
+pub struct EphemeralTokenCommand {
+    command: String,
+    refresh_margin: Duration,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl EphemeralTokenCommand {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            cached: Mutex::new(None),
+        }
+    }
+}
+
+impl AuthProvider for EphemeralTokenCommand {
+    fn credential(&self) -> BoxFuture<'_, Result<Credential>> {
+        Box::pin(async move {
+            let token = cached_or_fetch(&self.cached, self.refresh_margin, || async {
+                let output = run_command(&self.command).await?;
+                parse_ephemeral_token(&output)
+            })
+            .await?;
+            Ok(Credential::EphemeralToken(token))
+        })
+    }
+}
+
+/// OAuth access tokens printed by a command such as `gcloud auth print-access-token`
+pub struct BearerTokenCommand {
+    command: String,
+    /// How long a printed token is trusted before running the command again
+    lifetime: Duration,
+    refresh_margin: Duration,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl BearerTokenCommand {
+    pub fn new(command: impl Into<String>, lifetime: Duration) -> Self {
+        Self {
+            command: command.into(),
+            lifetime,
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            cached: Mutex::new(None),
+        }
+    }
+}
+
+impl AuthProvider for BearerTokenCommand {
+    fn credential(&self) -> BoxFuture<'_, Result<Credential>> {
+        Box::pin(async move {
+            let token = cached_or_fetch(&self.cached, self.refresh_margin, || async {
+                let token = run_command(&self.command).await?;
+                Ok((token, Instant::now() + self.lifetime))
+            })
+            .await?;
+            Ok(Credential::Bearer(token))
+        })
+    }
+}
+
+struct CachedToken {
+    value: String,
+    expires_at: Instant,
+}
+
+/// Reuse the cached token unless it expires within `margin`, otherwise fetch a new one.
+async fn cached_or_fetch<F, Fut>(
+    cache: &Mutex<Option<CachedToken>>,
+    margin: Duration,
+    fetch: F,
+) -> Result<String>
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<(String, Instant)>>,
+{
+    let mut cached = cache.lock().await;
+    if let Some(token) = cached.as_ref() {
+        if Instant::now() + margin < token.expires_at {
+            debug!("Reusing cached auth token");
+            return Ok(token.value.clone());
+        }
+    }
+
+    let (value, expires_at) = fetch().await?;
+    info!(
+        "Fetched new auth token, valid for {}s",
+        expires_at.saturating_duration_since(Instant::now()).as_secs()
+    );
+    *cached = Some(CachedToken {
+        value: value.clone(),
+        expires_at,
+    });
+    Ok(value)
+}
+
+/// Run a shell command and return its trimmed stdout.
+async fn run_command(command: &str) -> Result<String> {
+    let output = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .await
+        .with_context(|| format!("Failed to run token command `{}`", command))?;
+
+    if !output.status.success() {
+        bail!(
+            "Token command `{}` failed ({}): {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let token = String::from_utf8(output.stdout)
+        .context("Token command printed invalid UTF-8")?
+        .trim()
+        .to_string();
+    if token.is_empty() {
+        bail!("Token command `{}` printed nothing", command);
+    }
+    Ok(token)
+}
+
+/// Parse a bare token or an `AuthToken` JSON object into the token and its expiry.
+fn parse_ephemeral_token(output: &str) -> Result<(String, Instant)> {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(output) else {
+        return Ok((output.to_string(), Instant::now() + DEFAULT_EPHEMERAL_LIFETIME));
+    };
+
+    let token = json["name"]
+        .as_str()
+        .context("Ephemeral token JSON has no `name`")?
+        .to_string();
+
+    // Prefer the deadline for starting sessions, that is what a reconnect needs
+    let expire_time = json["newSessionExpireTime"]
+        .as_str()
+        .or_else(|| json["expireTime"].as_str());
+    let lifetime = match expire_time {
+        Some(time) => {
+            let expires = chrono::DateTime::parse_from_rfc3339(time)
+                .with_context(|| format!("Invalid token expiry `{}`", time))?;
+            (expires.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                .to_std()
+                .unwrap_or(Duration::ZERO)
+        }
+        None => DEFAULT_EPHEMERAL_LIFETIME,
+    };
+
+    Ok((token, Instant::now() + lifetime))
+}
+
+/// Mask credential query parameters so a URL can be logged safely.
+pub fn redact_url(url: &str) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+
+    let params: Vec<String> = query
+        .split('&')
+        .map(|param| match param.split_once('=') {
+            Some((name, _)) if SECRET_PARAMS.contains(&name) => format!("{}=REDACTED", name),
+            _ => param.to_string(),
+        })
+        .collect();
+
+    format!("{}?{}", base, params.join("&"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    #[test]
+    fn test_redact_url() {
+        assert_eq!(
+            redact_url("wss://host/path?alt=json&key=secret&access_token=t"),
+            "wss://host/path?alt=json&key=REDACTED&access_token=REDACTED"
+        );
+        assert_eq!(redact_url("wss://host/path"), "wss://host/path");
+    }
+
+    #[test]
+    fn test_credential_sets_header() {
+        let mut request = LIVE_API_URL.into_client_request().unwrap();
+        Credential::EphemeralToken("abc".into()).apply(&mut request).unwrap();
+        assert_eq!(request.headers()["authorization"], "Token abc");
+        assert!(request.uri().query().is_none());
+        assert_eq!(format!("{:?}", Credential::ApiKey("secret".into())), "ApiKey(<redacted>)");
+    }
+
+    #[test]
+    fn test_parse_ephemeral_token() {
+        let (token, expires_at) = parse_ephemeral_token("auth_tokens/plain").unwrap();
+        assert_eq!(token, "auth_tokens/plain");
+        assert!(expires_at > Instant::now() + Duration::from_secs(50));
+
+        let expire = (chrono::Utc::now() + chrono::Duration::minutes(5)).to_rfc3339();
+        let json = format!(r#"{{"name":"auth_tokens/json","expireTime":"{}"}}"#, expire);
+        let (token, expires_at) = parse_ephemeral_token(&json).unwrap();
+        assert_eq!(token, "auth_tokens/json");
+        assert!(expires_at > Instant::now() + Duration::from_secs(240));
+    }
+
+    #[tokio::test]
+    async fn test_token_refreshed_near_expiry() {
+        let cache = Mutex::new(None);
+        let margin = Duration::from_secs(10);
+
+        let first = cached_or_fetch(&cache, margin, || async {
+            Ok(("one".to_string(), Instant::now() + Duration::from_secs(60)))
+        })
+        .await
+        .unwrap();
+        let cached = cached_or_fetch(&cache, margin, || async { bail!("should use the cache") })
+            .await
+            .unwrap();
+        assert_eq!((first.as_str(), cached.as_str()), ("one", "one"));
+
+        // Within the margin: fetch again
+        cache.lock().await.as_mut().unwrap().expires_at = Instant::now() + Duration::from_secs(5);
+        let refreshed = cached_or_fetch(&cache, margin, || async {
+            Ok(("two".to_string(), Instant::now() + Duration::from_secs(60)))
+        })
+        .await
+        .unwrap();
+        assert_eq!(refreshed, "two");
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token_from_command() {
+        let provider = BearerTokenCommand::new("echo ya29.token", Duration::from_secs(3600));
+        match provider.credential().await.unwrap() {
+            Credential::Bearer(token) => assert_eq!(token, "ya29.token"),
+            other => panic!("unexpected credential: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! Escalation of hard questions to a stronger chat-completions model
+//!
+//! The live model answers quickly but not always well. When it calls the
+//! `escalate` tool, or ends a turn with the [`ESCALATE_MARKER`], the recent
+//! transcript and the latest screen frame are sent to an OpenAI-compatible
+//! `/chat/completions` endpoint. The answer is streamed into the overlay as a
+//! follow-up entry through [`WsInbound::Escalation`].
+
+use crate::gemini::{ApiResponse, FunctionBehavior, FunctionResponseScheduling};
+use crate::media_event::WsInbound;
+use crate::response_filter::NOTHING;
+use crate::session_context::ContextTurn;
+use crate::tools::Tool;
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose;
+use base64::Engine;
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// Text the live model writes to hand the rest of its turn to the stronger model
+pub const ESCALATE_MARKER: &str = "<escalate>";
+
+/// Name of the [`EscalateTool`] function
+pub const TOOL_NAME: &str = "escalate";
+
+/// Added to the live model's instructions when escalation is enabled
+pub const INSTRUCTION: &str = "If a question needs careful reasoning, such as a coding problem or a proof, call the escalate tool with the question (or write <escalate> followed by the question) instead of answering it yourself.";
+
+/// Turns of transcript sent along with an escalated question
+const MAX_TRANSCRIPT_TURNS: usize = 20;
+
+/// Where and how to ask the stronger model
+#[derive(Debug, Clone)]
+pub struct EscalationConfig {
+    /// Full chat-completions URL, e.g. `https://api.openai.com/v1/chat/completions`
+    pub url: String,
+    pub model: String,
+    /// Sent as a bearer token if set
+    pub api_key: Option<String>,
+    pub system_prompt: String,
+    /// Limit for the whole request, including streaming the answer
+    pub timeout: Duration,
+}
+
+impl Default for EscalationConfig {
+    fn default() -> Self {
+        Self {
+            url: "https://api.openai.com/v1/chat/completions".to_string(),
+            model: "gpt-4o".to_string(),
+            api_key: None,
+            system_prompt: "A realtime assistant watching the user's screen and conversation handed you this question because it needs careful thought. Answer it correctly and concisely; the user reads your answer in a small overlay.".to_string(),
+            timeout: Duration::from_secs(120),
+        }
+    }
+}
+
+/// Recent transcript and screen, shared with the tasks that see them
+#[derive(Debug, Clone, Default)]
+pub struct EscalationContext {
+    transcript: Arc<Mutex<VecDeque<ContextTurn>>>,
+    last_frame: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl EscalationContext {
+    pub fn record(&self, turn: ContextTurn) {
+        let mut transcript = self.transcript.lock().unwrap();
+        transcript.push_back(turn);
+        while transcript.len() > MAX_TRANSCRIPT_TURNS {
+            transcript.pop_front();
+        }
+    }
+
+    /// Remember the newest JPEG frame
+    pub fn set_frame(&self, jpeg: Vec<u8>) {
+        *self.last_frame.lock().unwrap() = Some(jpeg);
+    }
+
+    fn snapshot(&self) -> (Vec<ContextTurn>, Option<Vec<u8>>) {
+        let transcript = self.transcript.lock().unwrap().iter().cloned().collect();
+        (transcript, self.last_frame.lock().unwrap().clone())
+    }
+}
+
+/// What the user said and the model answered in the turn so far
+#[derive(Debug, Default)]
+struct TurnTranscript {
+    heard: String,
+    answer: String,
+}
+
+/// Asks the stronger model and streams its answers to the UI
+pub struct Escalator {
+    config: EscalationConfig,
+    context: EscalationContext,
+    http: reqwest::Client,
+    tx: mpsc::UnboundedSender<WsInbound>,
+    turn: Mutex<TurnTranscript>,
+    /// One escalation at a time
+    busy: AtomicBool,
+}
+
+impl Escalator {
+    pub fn new(
+        config: EscalationConfig,
+        context: EscalationContext,
+        tx: mpsc::UnboundedSender<WsInbound>,
+    ) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .context("Failed to create HTTP client")?;
+        Ok(Self {
+            config,
+            context,
+            http,
+            tx,
+            turn: Mutex::new(TurnTranscript::default()),
+            busy: AtomicBool::new(false),
+        })
+    }
+
+    pub fn context(&self) -> &EscalationContext {
+        &self.context
+    }
+
+    /// Track the live session's transcript, escalating when a turn ends with
+    /// the marker.
+    pub fn observe(self: &Arc<Self>, response: &ApiResponse) {
+        let mut turn = self.turn.lock().unwrap();
+        match response {
+            ApiResponse::InputTranscription(transcript) => turn.heard.push_str(&transcript.text),
+            ApiResponse::TextResponse { text, .. } => turn.answer.push_str(text),
+            ApiResponse::OutputTranscription(transcript) => turn.answer.push_str(&transcript.text),
+            ApiResponse::TurnComplete | ApiResponse::Interrupted => {
+                let TurnTranscript { heard, answer } = std::mem::take(&mut *turn);
+                let heard = heard.trim();
+                if !heard.is_empty() {
+                    self.context.record(ContextTurn::user(heard));
+                }
+
+                let (answer, question) = match answer.split_once(ESCALATE_MARKER) {
+                    Some((before, after)) => (before.trim(), Some(after.trim())),
+                    None => (answer.trim(), None),
+                };
+                if !answer.is_empty() && answer != NOTHING {
+                    self.context.record(ContextTurn::model(answer));
+                }
+
+                if let Some(question) = question {
+                    let question = (!question.is_empty()).then(|| question.to_string());
+                    let escalator = self.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = escalator.escalate(question).await {
+                            warn!("Escalation failed: {:#}", e);
+                            let _ = escalator.tx.send(WsInbound::Error(format!("Escalation failed: {:#}", e)));
+                        }
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Ask the stronger model about `question`, or about whatever the user is
+    /// working on if there is none, and return its full answer.
+    pub async fn escalate(&self, question: Option<String>) -> Result<String> {
+        if self.busy.swap(true, Ordering::SeqCst) {
+            bail!("An escalated question is already being answered");
+        }
+        info!("Escalating to {}: {:?}", self.config.model, question);
+
+        let result = self.stream_answer(question.as_deref()).await;
+        self.busy.store(false, Ordering::SeqCst);
+        // Close the overlay entry even if the answer was cut short
+        let _ = self.tx.send(WsInbound::Escalation {
+            content: String::new(),
+            is_final: true,
+        });
+        result
+    }
+
+    async fn stream_answer(&self, question: Option<&str>) -> Result<String> {
+        let (transcript, frame) = self.context.snapshot();
+        let body = self.request_body(question, &transcript, frame.as_deref());
+
+        let mut request = self.http.post(&self.config.url).json(&body);
+        if let Some(key) = &self.config.api_key {
+            request = request.bearer_auth(key);
+        }
+        let response = request
+            .send()
+            .await
+            .context("Chat completions request failed")?
+            .error_for_status()?;
+
+        // Server-sent events, one `data:` line per chunk
+        let mut answer = String::new();
+        let mut pending = Vec::new();
+        let mut body = response.bytes_stream();
+        while let Some(bytes) = body.next().await {
+            pending.extend_from_slice(&bytes?);
+            while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim();
+                if data == "[DONE]" {
+                    return Ok(answer);
+                }
+
+                let chunk: ChatChunk = serde_json::from_str(data).context("Invalid completion chunk")?;
+                let delta = chunk
+                    .choices
+                    .into_iter()
+                    .filter_map(|choice| choice.delta.content)
+                    .collect::<String>();
+                if !delta.is_empty() {
+                    answer.push_str(&delta);
+                    let _ = self.tx.send(WsInbound::Escalation {
+                        content: delta,
+                        is_final: false,
+                    });
+                }
+            }
+        }
+        Ok(answer)
+    }
+
+    fn request_body(&self, question: Option<&str>, transcript: &[ContextTurn], frame: Option<&[u8]>) -> Value {
+        let mut messages = vec![json!({ "role": "system", "content": self.config.system_prompt })];
+        messages.extend(transcript.iter().map(|turn| {
+            let role = if turn.role == "model" { "assistant" } else { "user" };
+            json!({ "role": role, "content": turn.text })
+        }));
+
+        let question = question.unwrap_or("Solve or answer whatever the user is working on right now.");
+        let mut content = vec![json!({ "type": "text", "text": question })];
+        if let Some(jpeg) = frame {
+            let url = format!("data:image/jpeg;base64,{}", general_purpose::STANDARD.encode(jpeg));
+            content.push(json!({ "type": "image_url", "image_url": { "url": url } }));
+        }
+        messages.push(json!({ "role": "user", "content": content }));
+
+        json!({
+            "model": self.config.model,
+            "stream": true,
+            "messages": messages,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: ChunkDelta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ChunkDelta {
+    content: Option<String>,
+}
+
+/// Lets the live model hand a question to the stronger model
+pub struct EscalateTool(pub Arc<Escalator>);
+
+impl Tool for EscalateTool {
+    fn name(&self) -> &str {
+        TOOL_NAME
+    }
+
+    fn description(&self) -> &str {
+        "Hands a hard question to a stronger model that also sees the screen. Its answer is shown to the user directly, so do not repeat it."
+    }
+
+    fn parameters(&self) -> Option<Value> {
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "question": {
+                    "type": "string",
+                    "description": "The question, with any details from the conversation needed to answer it",
+                },
+            },
+            "required": ["question"],
+        }))
+    }
+
+    fn behavior(&self) -> FunctionBehavior {
+        FunctionBehavior::NonBlocking
+    }
+
+    // The user already sees the answer
+    fn scheduling(&self) -> FunctionResponseScheduling {
+        FunctionResponseScheduling::Silent
+    }
+
+    fn invoke(&self, args: Value) -> BoxFuture<'static, Result<Value>> {
+        let escalator = self.0.clone();
+        Box::pin(async move {
+            let question = args["question"].as_str().map(str::to_string);
+            let answer = escalator.escalate(question).await?;
+            Ok(json!({ "answer": answer }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gemini::Transcript;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+
+    /// Serve one chat-completions request with these SSE chunks, handing back
+    /// the raw request
+    async fn stub_server(chunks: &[&str]) -> (String, oneshot::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/chat/completions", listener.local_addr().unwrap());
+        let mut events: String = chunks
+            .iter()
+            .map(|text| format!("data: {}\n\n", json!({ "choices": [{ "delta": { "content": text } }] })))
+            .collect();
+        events.push_str("data: [DONE]\n\n");
+
+        let (request_tx, request_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                        .and_then(|v| v.parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        let _ = request_tx.send(text);
+                        break;
+                    }
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n{}",
+                events
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+        (url, request_rx)
+    }
+
+    async fn collect_answer(rx: &mut mpsc::UnboundedReceiver<WsInbound>) -> Vec<String> {
+        let mut chunks = Vec::new();
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .expect("timed out waiting for the answer")
+                .unwrap();
+            match event {
+                WsInbound::Escalation { is_final: true, .. } => return chunks,
+                WsInbound::Escalation { content, .. } => chunks.push(content),
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+    }
+
+    fn escalator(url: String, tx: mpsc::UnboundedSender<WsInbound>) -> (Arc<Escalator>, EscalationContext) {
+        let context = EscalationContext::default();
+        let config = EscalationConfig {
+            url,
+            model: "big-model".to_string(),
+            api_key: Some("secret".to_string()),
+            ..Default::default()
+        };
+        (Arc::new(Escalator::new(config, context.clone(), tx).unwrap()), context)
+    }
+
+    #[tokio::test]
+    async fn test_escalation_sends_transcript_and_frame() {
+        let (url, request_rx) = stub_server(&["Use ", "a heap."]).await;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (escalator, context) = escalator(url, tx);
+        context.record(ContextTurn::user("how do I merge k lists"));
+        context.set_frame(vec![1, 2, 3]);
+
+        let answer = escalator.escalate(Some("merge k sorted lists".into())).await.unwrap();
+        assert_eq!(answer, "Use a heap.");
+        assert_eq!(collect_answer(&mut rx).await, ["Use ", "a heap."]);
+
+        let request = request_rx.await.unwrap();
+        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+        assert!(head.to_lowercase().contains("authorization: bearer secret"));
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["model"], "big-model");
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["messages"][1], json!({ "role": "user", "content": "how do I merge k lists" }));
+        assert_eq!(body["messages"][2]["content"][0]["text"], "merge k sorted lists");
+        assert_eq!(
+            body["messages"][2]["content"][1]["image_url"]["url"],
+            "data:image/jpeg;base64,AQID"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_marker_escalates_at_turn_end() {
+        let (url, request_rx) = stub_server(&["42"]).await;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (escalator, context) = escalator(url, tx);
+
+        escalator.observe(&ApiResponse::InputTranscription(Transcript {
+            text: "what is six times seven".to_string(),
+            is_final: true,
+        }));
+        for text in ["Let me check. <esc", "alate> six times", " seven"] {
+            escalator.observe(&ApiResponse::TextResponse {
+                text: text.to_string(),
+                is_complete: false,
+            });
+        }
+        escalator.observe(&ApiResponse::TurnComplete);
+
+        assert_eq!(collect_answer(&mut rx).await, ["42"]);
+        let request = request_rx.await.unwrap();
+        assert!(request.contains("six times seven"));
+        assert_eq!(
+            context.snapshot().0,
+            [
+                ContextTurn::user("what is six times seven"),
+                ContextTurn::model("Let me check.")
+            ]
+        );
+    }
+}
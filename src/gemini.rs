@@ -19,38 +19,414 @@ pub struct GenerationConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub media_resolution: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub speech_config: Option<serde_json::Value>,
+    pub speech_config: Option<SpeechConfig>,
+}
+
+/// Prebuilt voices the Live API can speak with
+pub const LIVE_VOICES: &[&str] = &[
+    "Puck", "Charon", "Kore", "Fenrir", "Aoede", "Leda", "Orus", "Zephyr",
+];
+
+/// Voice and language of spoken responses
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeechConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice_config: Option<VoiceConfig>,
+    /// BCP-47 code such as `de-DE`; native audio models pick the language themselves
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prebuilt_voice_config: Option<PrebuiltVoiceConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PrebuiltVoiceConfig {
+    pub voice_name: String,
+}
+
+impl SpeechConfig {
+    /// Build a speech config, checking the voice against [`LIVE_VOICES`]
+    /// (case-insensitively) and the shape of the language code.
+    // GeminiError is large because it carries WebSocket errors inline
+    #[allow(clippy::result_large_err)]
+    pub fn new(voice: Option<&str>, language_code: Option<&str>) -> Result<Self> {
+        let voice_config = match voice {
+            Some(voice) => {
+                let Some(known) = LIVE_VOICES
+                    .iter()
+                    .find(|known| known.eq_ignore_ascii_case(voice))
+                else {
+                    return Err(GeminiError::InvalidConfig(format!(
+                        "Unknown voice `{}`, expected one of: {}",
+                        voice,
+                        LIVE_VOICES.join(", ")
+                    )));
+                };
+                Some(VoiceConfig {
+                    prebuilt_voice_config: Some(PrebuiltVoiceConfig {
+                        voice_name: known.to_string(),
+                    }),
+                })
+            }
+            None => None,
+        };
+
+        if let Some(code) = language_code {
+            if !is_language_code(code) {
+                return Err(GeminiError::InvalidConfig(format!(
+                    "Invalid language code `{}`, expected e.g. `en-US`",
+                    code
+                )));
+            }
+        }
+
+        Ok(Self {
+            voice_config,
+            language_code: language_code.map(str::to_string),
+        })
+    }
+}
+
+/// `ll-RR` or `lll-RR`, e.g. `en-US` or `cmn-CN`
+fn is_language_code(code: &str) -> bool {
+    match code.split_once('-') {
+        Some((language, region)) => {
+            (2..=3).contains(&language.len())
+                && language.chars().all(|c| c.is_ascii_lowercase())
+                && region.len() == 2
+                && region.chars().all(|c| c.is_ascii_uppercase())
+        }
+        None => false,
+    }
 }
 
 /// Content structure for system instructions and messages
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Content {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub role: Option<String>, // "SYSTEM" | "USER" | "MODEL"
+    #[serde(default)]
     pub parts: Vec<Part>,
 }
 
-/// Part of a content message
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+/// Part of a content message, used in both directions
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct Part {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inline_data: Option<Blob>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub executable_code: Option<ExecutableCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_execution_result: Option<CodeExecutionResult>,
+    /// Set when the part is the model's reasoning rather than its answer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thought: Option<bool>,
+    /// Fields this client does not know about yet, kept as received
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Inline media bytes
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Blob {
+    pub mime_type: String,
+    /// Base64 encoded data
+    pub data: String,
+}
+
+/// Code generated by the model for the code execution tool
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutableCode {
+    #[serde(default)]
+    pub language: String,
+    #[serde(default)]
+    pub code: String,
+}
+
+/// Result of running an `ExecutableCode` part
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeExecutionResult {
+    /// e.g. OUTCOME_OK, OUTCOME_FAILED, OUTCOME_DEADLINE_EXCEEDED
+    #[serde(default)]
+    pub outcome: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+/// Content of a `serverContent` message
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ServerContent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_turn: Option<Content>,
+    pub generation_complete: bool,
+    pub turn_complete: bool,
+    pub interrupted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_transcription: Option<Transcript>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_transcription: Option<Transcript>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grounding_metadata: Option<GroundingMetadata>,
+    /// Fields this client does not know about yet, kept as received
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Sources the model used when answering with the search tool
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GroundingMetadata {
+    pub web_search_queries: Vec<String>,
+    pub grounding_chunks: Vec<GroundingChunk>,
+    /// Fields this client does not know about yet, kept as received
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A single grounding source
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GroundingChunk {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web: Option<WebSource>,
+}
+
+/// A web page used for grounding
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebSource {
+    pub uri: String,
+    pub title: String,
 }
 
 /// Session setup message.
+///
+/// Sent first with the model set; later setup messages may update the
+/// configuration mid-session and leave the model empty.
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct BidiGenerateContentSetup {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub model: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub generation_config: Option<GenerationConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system_instruction: Option<Content>, // Changed from String to Content
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tools: Option<serde_json::Value>,
+    pub tools: Option<Vec<ToolDeclaration>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realtime_input_config: Option<RealtimeInputConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_resumption: Option<SessionResumptionConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_audio_transcription: Option<AudioTranscriptionConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_audio_transcription: Option<AudioTranscriptionConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_window_compression: Option<ContextWindowCompressionConfig>,
+}
+
+/// A set of tools made available to the model in setup
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolDeclaration {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub function_declarations: Vec<FunctionDeclaration>,
+    /// Built-in tool that lets the model write and run Python
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_execution: Option<CodeExecution>,
+    /// Built-in tool that grounds answers in Google Search results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub google_search: Option<GoogleSearch>,
+}
+
+/// Enables the built-in code execution tool. Has no options.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct CodeExecution {}
+
+/// Enables the built-in Google Search tool. Has no options.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct GoogleSearch {}
+
+/// Declaration of a client-side function the model may call
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FunctionDeclaration {
+    pub name: String,
+    pub description: String,
+    /// JSON schema (OpenAPI subset) of the arguments
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub behavior: Option<FunctionBehavior>,
+}
+
+/// Whether the model waits for a function's result before continuing
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FunctionBehavior {
+    #[default]
+    Blocking,
+    NonBlocking,
+}
+
+/// How the model should treat the result of a non-blocking function
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FunctionResponseScheduling {
+    /// Interrupt the current generation and report the result right away
+    Interrupt,
+    /// Report the result once the current generation is finished
+    WhenIdle,
+    /// Absorb the result without reacting to it
+    Silent,
+}
+
+/// Session resumption configuration. Passing a handle resumes a previous session.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResumptionConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
+}
+
+/// Enables server-side transcription of an audio stream. Has no options yet.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct AudioTranscriptionConfig {}
+
+/// Lets the server discard old context instead of closing the session at the limit
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextWindowCompressionConfig {
+    /// Context size that triggers compression, server default if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sliding_window: Option<SlidingWindow>,
+}
+
+impl ContextWindowCompressionConfig {
+    /// Sliding-window compression from `trigger_tokens` down to `target_tokens`
+    pub fn sliding_window(trigger_tokens: u64, target_tokens: u64) -> Self {
+        Self {
+            trigger_tokens: Some(trigger_tokens),
+            sliding_window: Some(SlidingWindow {
+                target_tokens: Some(target_tokens),
+            }),
+        }
+    }
+}
+
+/// Keeps the most recent turns, dropping the oldest ones first
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SlidingWindow {
+    /// Context size to shrink to, server default if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_tokens: Option<u64>,
+}
+
+/// Realtime input behaviour configured in setup
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RealtimeInputConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub automatic_activity_detection: Option<AutomaticActivityDetection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activity_handling: Option<ActivityHandling>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub turn_coverage: Option<TurnCoverage>,
+}
+
+/// Server-side voice activity detection settings
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomaticActivityDetection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+}
+
+/// What effect the start of user activity has on a running generation
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ActivityHandling {
+    StartOfActivityInterrupts,
+    NoInterruption,
+}
+
+/// Which input is included in the user's turn
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TurnCoverage {
+    TurnIncludesOnlyActivity,
+    TurnIncludesAllInput,
+}
+
+/// Incremental conversation content appended to the session history
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientContent {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub turns: Vec<Content>,
+    #[serde(default)]
+    pub turn_complete: bool,
+}
+
+/// Server request to execute one or more function calls
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCall {
+    #[serde(default)]
+    pub function_calls: Vec<FunctionCall>,
+}
+
+/// A single function call requested by the model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FunctionCall {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+/// Client response to a server tool call
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolResponse {
+    pub function_responses: Vec<FunctionResponse>,
+}
+
+/// Result of a single function call, matched to the call by `id`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FunctionResponse {
+    pub id: String,
+    pub name: String,
+    pub response: serde_json::Value,
+    /// Only meaningful for non-blocking functions
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub realtime_input_config: Option<serde_json::Value>,
+    pub scheduling: Option<FunctionResponseScheduling>,
+}
+
+/// Server notice that previously issued function calls should be cancelled
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct ToolCallCancellation {
+    #[serde(default)]
+    pub ids: Vec<String>,
 }
 
 /// A chunk of realtime input (audio/video/text)
@@ -92,7 +468,7 @@ pub enum ClientMessage {
     Setup { setup: BidiGenerateContentSetup },
     ClientContent { 
         #[serde(rename = "clientContent")]
-        client_content: serde_json::Value 
+        client_content: ClientContent 
     },
     RealtimeInput { 
         #[serde(rename = "realtimeInput")]
@@ -100,7 +476,7 @@ pub enum ClientMessage {
     },
     ToolResponse { 
         #[serde(rename = "toolResponse")]
-        tool_response: serde_json::Value 
+        tool_response: ToolResponse 
     },
 }
 
@@ -114,15 +490,17 @@ pub enum ServerMessage {
     },
     ServerContent {
         #[serde(rename = "serverContent")]
-        server_content: serde_json::Value,
+        server_content: Box<ServerContent>,
+        #[serde(rename = "usageMetadata", default, skip_serializing_if = "Option::is_none")]
+        usage_metadata: Option<UsageMetadata>,
     },
     ToolCall {
         #[serde(rename = "toolCall")]
-        tool_call: serde_json::Value,
+        tool_call: ToolCall,
     },
     ToolCallCancellation {
         #[serde(rename = "toolCallCancellation")]
-        tool_call_cancellation: serde_json::Value,
+        tool_call_cancellation: ToolCallCancellation,
     },
     GoAway {
         #[serde(rename = "goAway")]
@@ -132,8 +510,39 @@ pub enum ServerMessage {
         #[serde(rename = "sessionResumptionUpdate")]
         session_resumption_update: serde_json::Value,
     },
+    /// Token usage sent on its own, outside any other message
+    UsageMetadata {
+        #[serde(rename = "usageMetadata")]
+        usage_metadata: UsageMetadata,
+    },
+}
+
+/// Token usage reported by the server for a response
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UsageMetadata {
+    /// Size of the context, including history, for this turn
+    pub prompt_token_count: u64,
+    pub cached_content_token_count: u64,
+    pub response_token_count: u64,
+    pub tool_use_prompt_token_count: u64,
+    pub thoughts_token_count: u64,
+    pub total_token_count: u64,
+    pub prompt_tokens_details: Vec<ModalityTokenCount>,
+    pub response_tokens_details: Vec<ModalityTokenCount>,
+}
+
+/// Token count for a single modality (TEXT, AUDIO, IMAGE, VIDEO, ...)
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ModalityTokenCount {
+    pub modality: String,
+    pub token_count: u64,
 }
 
+/// Longest wait between reconnect attempts, also used after rate limiting
+pub const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
 /// Error type for Gemini API operations
 #[derive(Debug, thiserror::Error)]
 pub enum GeminiError {
@@ -158,6 +567,15 @@ pub enum GeminiError {
     #[error("Timeout")]
     Timeout,
 
+    #[error("Authentication error: {0}")]
+    Auth(String),
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+
+    #[error(transparent)]
+    Server(#[from] ServerError),
+
     #[error("Other error: {0}")]
     Other(String),
 }
@@ -173,15 +591,244 @@ impl Clone for GeminiError {
             Self::SetupNotComplete => Self::SetupNotComplete,
             Self::ChannelClosed => Self::ChannelClosed,
             Self::Timeout => Self::Timeout,
+            Self::Auth(s) => Self::Auth(s.clone()),
+            Self::InvalidConfig(s) => Self::InvalidConfig(s.clone()),
+            Self::Server(e) => Self::Server(e.clone()),
             Self::Other(s) => Self::Other(s.clone()),
         }
     }
 }
 
+impl GeminiError {
+    /// Classify a failed WebSocket handshake by its HTTP status and error payload.
+    pub fn from_handshake(error: WsError) -> Self {
+        let WsError::Http(response) = &error else {
+            return Self::WebSocket(error);
+        };
+        let status = response.status().as_u16();
+        let body = response.body().as_deref().unwrap_or_default();
+        let payload = serde_json::from_slice::<ErrorPayload>(body).ok();
+        let message = payload
+            .as_ref()
+            .map(|payload| payload.error.message.clone())
+            .unwrap_or_else(|| format!("HTTP {}", response.status()));
+        if status == 401 || status == 403 {
+            return Self::Auth(message);
+        }
+
+        let kind = payload
+            .as_ref()
+            .and_then(|payload| payload.error.kind())
+            .or(match status {
+                400 => Some(ServerErrorKind::InvalidArgument),
+                404 => Some(ServerErrorKind::ModelNotFound),
+                429 => Some(ServerErrorKind::RateLimited),
+                500..=599 => Some(ServerErrorKind::Internal),
+                _ => None,
+            });
+        match kind {
+            Some(kind) => Self::Server(ServerError { kind, message }),
+            None => Self::WebSocket(error),
+        }
+    }
+
+    /// What to do about this error when it ends a connection
+    pub fn retry(&self) -> Retry {
+        match self {
+            Self::Server(e) => e.kind.retry(),
+            Self::Auth(_) | Self::InvalidConfig(_) => Retry::Fatal,
+            _ => Retry::Resume,
+        }
+    }
+}
+
+/// Whether reconnecting after an error can help
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retry {
+    /// Reconnect and resume the session
+    Resume,
+    /// Reconnect with a new session, the old one cannot be resumed
+    Fresh,
+    /// Reconnect and resume after waiting the longest reconnect delay
+    Backoff,
+    /// Give up, the same request would fail again
+    Fatal,
+}
+
+/// Kinds of errors the server reports in close frames and error payloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerErrorKind {
+    InvalidArgument,
+    /// Too many requests for now, e.g. a per-minute limit
+    RateLimited,
+    /// The quota is used up until the plan or billing changes
+    QuotaExhausted,
+    ModelNotFound,
+    SessionExpired,
+    Internal,
+}
+
+impl ServerErrorKind {
+    /// Recognize a status name or error code, e.g. `RESOURCE_EXHAUSTED` or
+    /// OpenAI's `session_expired`
+    pub fn from_status(status: &str) -> Option<Self> {
+        match status.to_ascii_uppercase().as_str() {
+            "INVALID_ARGUMENT" | "FAILED_PRECONDITION" | "INVALID_REQUEST_ERROR" => {
+                Some(Self::InvalidArgument)
+            }
+            "RESOURCE_EXHAUSTED" | "RATE_LIMIT_EXCEEDED" => Some(Self::RateLimited),
+            "INSUFFICIENT_QUOTA" => Some(Self::QuotaExhausted),
+            // Only the model is looked up before a session exists
+            "NOT_FOUND" | "MODEL_NOT_FOUND" => Some(Self::ModelNotFound),
+            "SESSION_EXPIRED" => Some(Self::SessionExpired),
+            "INTERNAL" | "UNAVAILABLE" | "DEADLINE_EXCEEDED" | "SERVER_ERROR" => {
+                Some(Self::Internal)
+            }
+            _ => None,
+        }
+    }
+
+    /// Recognize a free-text reason such as "You exceeded your current quota",
+    /// for errors that come without a status.
+    pub fn from_text(text: &str) -> Option<Self> {
+        let text = text.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|p| text.contains(p));
+        if has(&["exceeded your current quota", "quota exceeded", "insufficient_quota", "billing"]) {
+            Some(Self::QuotaExhausted)
+        } else if has(&[
+            "rate limit",
+            "rate_limit",
+            "resource_exhausted",
+            "resource has been exhausted",
+            "too many requests",
+        ]) {
+            Some(Self::RateLimited)
+        } else if has(&["not found", "not_found"]) {
+            // Unknown sessions and resumption handles are not found either;
+            // only a missing model is worth giving up for
+            if has(&["model"]) {
+                Some(Self::ModelNotFound)
+            } else {
+                Some(Self::SessionExpired)
+            }
+        } else if text.contains("session") && has(&["expired", "invalid"]) {
+            Some(Self::SessionExpired)
+        } else if has(&["invalid argument", "invalid_argument"]) {
+            Some(Self::InvalidArgument)
+        } else if has(&["internal", "unavailable", "deadline"]) {
+            Some(Self::Internal)
+        } else {
+            None
+        }
+    }
+
+    pub fn retry(self) -> Retry {
+        match self {
+            Self::InvalidArgument | Self::QuotaExhausted | Self::ModelNotFound => Retry::Fatal,
+            Self::RateLimited => Retry::Backoff,
+            Self::SessionExpired => Retry::Fresh,
+            Self::Internal => Retry::Resume,
+        }
+    }
+}
+
+impl std::fmt::Display for ServerErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::InvalidArgument => "Invalid argument",
+            Self::RateLimited => "Rate limited",
+            Self::QuotaExhausted => "Quota exhausted",
+            Self::ModelNotFound => "Model not found",
+            Self::SessionExpired => "Session expired",
+            Self::Internal => "Server error",
+        })
+    }
+}
+
+/// An error reported by the server
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{kind}: {message}")]
+pub struct ServerError {
+    pub kind: ServerErrorKind,
+    pub message: String,
+}
+
+impl ServerError {
+    /// Classify a close frame, `None` for a close that reports no error.
+    ///
+    /// A close code that names the error decides; generic codes such as
+    /// 1008 (policy violation) and 1011 (internal error) are refined by the
+    /// reason text.
+    pub fn from_close(code: u16, reason: &str) -> Option<Self> {
+        let kind = match code {
+            1007 => Some(ServerErrorKind::InvalidArgument),
+            1013 => Some(ServerErrorKind::RateLimited),
+            _ => None,
+        }
+        .or_else(|| ServerErrorKind::from_text(reason))
+        .or(match code {
+            1011 | 1014 => Some(ServerErrorKind::Internal),
+            _ => None,
+        })?;
+        let message = if reason.is_empty() {
+            format!("connection closed with code {}", code)
+        } else {
+            reason.to_string()
+        };
+        Some(Self { kind, message })
+    }
+}
+
+/// Error body of a rejected request, as sent by Google and OpenAI APIs
+#[derive(Debug, Deserialize)]
+pub struct ErrorPayload {
+    pub error: ErrorDetail,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ErrorDetail {
+    #[serde(default)]
+    pub message: String,
+    /// gRPC status name such as `RESOURCE_EXHAUSTED`
+    #[serde(default)]
+    pub status: Option<String>,
+    /// OpenAI error type such as `invalid_request_error`
+    #[serde(default, rename = "type")]
+    pub error_type: Option<String>,
+    /// Numeric HTTP code from Google, a string such as `session_expired` from OpenAI
+    #[serde(default)]
+    pub code: Option<serde_json::Value>,
+}
+
+impl ErrorDetail {
+    /// The kind named by the status, code or type, most specific first.
+    /// The message is only read when none of them is given.
+    pub fn kind(&self) -> Option<ServerErrorKind> {
+        let code = self.code.as_ref().and_then(|code| code.as_str());
+        let fields = [self.status.as_deref(), code, self.error_type.as_deref()];
+        if fields.iter().all(Option::is_none) {
+            return ServerErrorKind::from_text(&self.message);
+        }
+        fields.into_iter().flatten().find_map(ServerErrorKind::from_status)
+    }
+
+    /// The detail as a typed error, or `Other` if its kind is unknown
+    pub fn into_error(self) -> GeminiError {
+        match self.kind() {
+            Some(kind) => GeminiError::Server(ServerError {
+                kind,
+                message: self.message,
+            }),
+            None => GeminiError::Other(self.message),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, GeminiError>;
 
 /// Transcript from the Gemini API
-#[derive(Debug, Clone)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Transcript {
     pub text: String,
     pub is_final: bool,
@@ -206,20 +853,44 @@ pub enum ApiResponse {
     AudioResponse { data: Vec<u8>, is_complete: bool },
 
     /// Model is requesting a tool call
-    ToolCall(serde_json::Value),
+    ToolCall(ToolCall),
 
-    /// Model has cancelled a tool call
-    ToolCallCancellation(String),
+    /// Model has cancelled the tool calls with these ids
+    ToolCallCancellation(Vec<String>),
 
-    /// Server will disconnect soon
-    GoAway,
+    /// Server will disconnect soon, with the time left before it does
+    GoAway(Option<Duration>),
 
     /// Session resumption token provided
     SessionResumptionUpdate(String),
 
+    /// Model reasoning, not part of the answer
+    Thought(String),
+
+    /// Code the model runs with the code execution tool
+    ExecutableCode(ExecutableCode),
+
+    /// Output of code the model ran
+    CodeExecutionResult(CodeExecutionResult),
+
+    /// Search queries and sources behind a grounded answer
+    Grounding(GroundingMetadata),
+
     /// Generation of a response is complete
     GenerationComplete,
 
+    /// The response in progress was cut off by new user activity
+    Interrupted,
+
+    /// The model has finished its turn and is waiting for input
+    TurnComplete,
+
+    /// Token usage for the current response
+    Usage(UsageMetadata),
+
+    /// The server compressed the context window, shrinking the prompt
+    ContextCompressed { before_tokens: u64, after_tokens: u64 },
+
     /// Special message indicating connection closed, should trigger client cleanup
     ConnectionClosed,
 }
@@ -227,14 +898,43 @@ pub enum ApiResponse {
 /// Configuration for the Gemini client
 #[derive(Debug, Clone)]
 pub struct GeminiClientConfig {
+    /// Endpoint override; empty means the backend's own endpoint
     pub url: String,
+    /// Model name, with or without the `models/` prefix
     pub model: String,
+    /// Which Google API serves the session
+    pub backend: Backend,
     pub response_modality: ResponseModality,
     pub system_instruction: Option<String>,
     pub temperature: Option<f32>,
     pub media_resolution: Option<MediaResolution>,
+    /// Voice and language for audio responses
+    pub speech: Option<SpeechConfig>,
+    /// Turns sent as `clientContent` after setting up a new (not resumed) session
+    pub initial_context: Vec<Content>,
+    pub tools: Vec<ToolDeclaration>,
+    /// Let the model run code server-side
+    pub code_execution: bool,
+    /// Let the model ground answers with Google Search
+    pub google_search: bool,
+    pub session_resumption: bool,
+    /// Ask the server to transcribe the audio we send
+    pub input_transcription: bool,
+    /// Ask the server to transcribe spoken responses (audio modality only)
+    pub output_transcription: bool,
+    /// Compress old context instead of hitting the limit, `None` to disable
+    pub context_window_compression: Option<ContextWindowCompressionConfig>,
     pub reconnect_attempts: usize,
     pub reconnect_delay: Duration,
+    /// Ping the server this often, `None` to disable keepalive
+    pub keepalive_interval: Option<Duration>,
+    /// Drop a connection that sent nothing, not even a pong, for this long after a ping
+    pub pong_timeout: Duration,
+    /// Drop a connection whose server stays silent this long after activityEnd
+    pub response_timeout: Option<Duration>,
+    /// Hand over to a standby session this long after connecting, ahead of
+    /// the server's limit; `None` to wait for GoAway
+    pub connection_lifetime: Option<Duration>,
 }
 
 impl Default for GeminiClientConfig {
@@ -242,12 +942,30 @@ impl Default for GeminiClientConfig {
         Self {
             url: String::new(),
             model: "models/gemini-2.0-flash-live-001".to_string(),
+            backend: Backend::GeminiApi,
             response_modality: ResponseModality::Text,
             system_instruction: None,
             temperature: Some(0.7),
             media_resolution: Some(MediaResolution::Medium),
+            speech: None,
+            initial_context: Vec::new(),
+            tools: Vec::new(),
+            code_execution: false,
+            google_search: false,
+            session_resumption: true,
+            input_transcription: true,
+            output_transcription: true,
+            // Audio and video fill the 32k context quickly; keep the most recent half
+            context_window_compression: Some(ContextWindowCompressionConfig::sliding_window(
+                25_600, 12_800,
+            )),
             reconnect_attempts: 3,
             reconnect_delay: Duration::from_secs(1),
+            keepalive_interval: Some(Duration::from_secs(15)),
+            pong_timeout: Duration::from_secs(10),
+            response_timeout: Some(Duration::from_secs(30)),
+            // Connections last about ten minutes
+            connection_lifetime: Some(Duration::from_secs(9 * 60)),
         }
     }
 }
@@ -269,7 +987,8 @@ impl ResponseModality {
 }
 
 /// Media resolution options for video input
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum MediaResolution {
     Low,
     Medium,
@@ -285,3 +1004,324 @@ impl MediaResolution {
         }
     }
 }
+
+/// API that hosts the Live session
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Gemini API (generativelanguage.googleapis.com), API key or token auth
+    #[default]
+    GeminiApi,
+    /// Vertex AI in a Google Cloud project, OAuth bearer token auth
+    VertexAi { project: String, location: String },
+}
+
+impl Backend {
+    /// Live WebSocket endpoint, `None` to use the one matching the credential
+    pub fn url(&self) -> Option<String> {
+        match self {
+            Backend::GeminiApi => None,
+            Backend::VertexAi { location, .. } => {
+                let host = if location == "global" {
+                    "aiplatform.googleapis.com".to_string()
+                } else {
+                    format!("{}-aiplatform.googleapis.com", location)
+                };
+                Some(format!(
+                    "wss://{}/ws/google.cloud.aiplatform.v1.LlmBidiService/BidiGenerateContent",
+                    host
+                ))
+            }
+        }
+    }
+
+    /// Full model resource name for the setup message
+    pub fn model_path(&self, model: &str) -> String {
+        // Names that already carry a resource path are used as given
+        let name = model.strip_prefix("models/").unwrap_or(model);
+        if name.contains('/') {
+            return name.to_string();
+        }
+        match self {
+            Backend::GeminiApi => format!("models/{}", name),
+            Backend::VertexAi { project, location } => format!(
+                "projects/{}/locations/{}/publishers/google/models/{}",
+                project, location, name
+            ),
+        }
+    }
+}
+
+/// Parse a protobuf JSON duration such as `"10s"` or `"0.5s"`.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let secs: f64 = value.strip_suffix('s')?.parse().ok()?;
+    if secs.is_finite() && secs >= 0.0 {
+        Some(Duration::from_secs_f64(secs))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("10s"), Some(Duration::from_secs(10)));
+        assert_eq!(parse_duration("0.5s"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("10"), None);
+        assert_eq!(parse_duration("-1s"), None);
+    }
+
+    #[test]
+    fn test_vertex_backend_endpoint_and_model() {
+        let vertex = Backend::VertexAi {
+            project: "my-project".to_string(),
+            location: "us-central1".to_string(),
+        };
+        assert_eq!(
+            vertex.url().unwrap(),
+            "wss://us-central1-aiplatform.googleapis.com/ws/google.cloud.aiplatform.v1.LlmBidiService/BidiGenerateContent"
+        );
+        assert_eq!(
+            vertex.model_path("models/gemini-2.0-flash-live-001"),
+            "projects/my-project/locations/us-central1/publishers/google/models/gemini-2.0-flash-live-001"
+        );
+        assert_eq!(Backend::GeminiApi.url(), None);
+        assert_eq!(
+            Backend::GeminiApi.model_path("gemini-2.0-flash-live-001"),
+            "models/gemini-2.0-flash-live-001"
+        );
+    }
+
+    #[test]
+    fn test_speech_config() {
+        let speech = SpeechConfig::new(Some("kore"), Some("de-DE")).unwrap();
+        assert_eq!(
+            serde_json::to_value(&speech).unwrap(),
+            serde_json::json!({
+                "voiceConfig": { "prebuiltVoiceConfig": { "voiceName": "Kore" } },
+                "languageCode": "de-DE"
+            })
+        );
+
+        assert!(matches!(
+            SpeechConfig::new(Some("Alloy"), None),
+            Err(GeminiError::InvalidConfig(_))
+        ));
+        assert!(SpeechConfig::new(None, Some("german")).is_err());
+        assert!(SpeechConfig::new(None, Some("cmn-CN")).is_ok());
+    }
+
+    #[test]
+    fn test_setup_serializes_session_resumption() {
+        let setup = BidiGenerateContentSetup {
+            model: "models/test".to_string(),
+            session_resumption: Some(SessionResumptionConfig {
+                handle: Some("abc".to_string()),
+            }),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&setup).unwrap();
+        assert_eq!(json["sessionResumption"]["handle"], "abc");
+        assert!(json.get("realtimeInputConfig").is_none());
+    }
+
+    #[test]
+    fn test_setup_serializes_transcription() {
+        let setup = BidiGenerateContentSetup {
+            model: "models/test".to_string(),
+            input_audio_transcription: Some(AudioTranscriptionConfig::default()),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&setup).unwrap();
+        assert_eq!(json["inputAudioTranscription"], serde_json::json!({}));
+        assert!(json.get("outputAudioTranscription").is_none());
+    }
+
+    #[test]
+    fn test_setup_serializes_context_window_compression() {
+        let setup = BidiGenerateContentSetup {
+            context_window_compression: Some(ContextWindowCompressionConfig::sliding_window(
+                1000, 500,
+            )),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&setup).unwrap();
+        assert_eq!(
+            json["contextWindowCompression"],
+            serde_json::json!({ "triggerTokens": 1000, "slidingWindow": { "targetTokens": 500 } })
+        );
+    }
+
+    #[test]
+    fn test_server_content_keeps_usage_metadata() {
+        let msg: ServerMessage = serde_json::from_str(
+            r#"{"serverContent":{"turnComplete":true},"usageMetadata":{"promptTokenCount":42}}"#,
+        )
+        .unwrap();
+        match msg {
+            ServerMessage::ServerContent { usage_metadata, .. } => {
+                assert_eq!(usage_metadata.unwrap().prompt_token_count, 42)
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+
+        let msg: ServerMessage = serde_json::from_str(
+            r#"{"usageMetadata":{"promptTokenCount":7,"promptTokensDetails":[{"modality":"AUDIO","tokenCount":5}]}}"#,
+        )
+        .unwrap();
+        match msg {
+            ServerMessage::UsageMetadata { usage_metadata } => assert_eq!(
+                usage_metadata.prompt_tokens_details,
+                vec![ModalityTokenCount {
+                    modality: "AUDIO".to_string(),
+                    token_count: 5,
+                }]
+            ),
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_built_in_tools_serialize_as_empty_objects() {
+        let tools = vec![
+            ToolDeclaration {
+                code_execution: Some(CodeExecution::default()),
+                ..Default::default()
+            },
+            ToolDeclaration {
+                google_search: Some(GoogleSearch::default()),
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            serde_json::to_value(&tools).unwrap(),
+            serde_json::json!([{ "codeExecution": {} }, { "googleSearch": {} }])
+        );
+    }
+
+    #[test]
+    fn test_config_update_omits_model() {
+        let msg = ClientMessage::Setup {
+            setup: BidiGenerateContentSetup {
+                realtime_input_config: Some(RealtimeInputConfig {
+                    activity_handling: Some(ActivityHandling::StartOfActivityInterrupts),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        };
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "setup": {
+                    "realtimeInputConfig": { "activityHandling": "START_OF_ACTIVITY_INTERRUPTS" }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_classify_server_errors() {
+        let close = |code, reason| ServerError::from_close(code, reason).map(|e| e.kind);
+        assert_eq!(
+            close(1007, "Request contains an invalid argument."),
+            Some(ServerErrorKind::InvalidArgument)
+        );
+        assert_eq!(
+            close(1011, "You exceeded your current quota, please check your plan."),
+            Some(ServerErrorKind::QuotaExhausted)
+        );
+        assert_eq!(
+            close(1008, "models/gemini-0.1 is not found for API version v1beta"),
+            Some(ServerErrorKind::ModelNotFound)
+        );
+        assert_eq!(close(1008, "Session not found."), Some(ServerErrorKind::SessionExpired));
+        // A stale resumption handle must not end the app
+        assert_eq!(
+            close(1008, "Requested entity was not found."),
+            Some(ServerErrorKind::SessionExpired)
+        );
+        assert_eq!(
+            close(1011, "Rate limit exceeded, please try again later."),
+            Some(ServerErrorKind::RateLimited)
+        );
+        // The close code wins over a reason that says otherwise
+        assert_eq!(
+            close(1007, "Invalid session handle"),
+            Some(ServerErrorKind::InvalidArgument)
+        );
+        assert_eq!(close(1011, ""), Some(ServerErrorKind::Internal));
+        assert_eq!(close(1000, ""), None);
+        assert_eq!(close(1001, "going away"), None);
+
+        let detail: ErrorPayload = serde_json::from_str(
+            r#"{"error": {"code": 429, "message": "Resource has been exhausted", "status": "RESOURCE_EXHAUSTED"}}"#,
+        )
+        .unwrap();
+        assert_eq!(detail.error.kind(), Some(ServerErrorKind::RateLimited));
+        // Structured fields decide, the message is only a fallback
+        let detail: ErrorPayload = serde_json::from_str(
+            r#"{"error": {"message": "Requested entity was not found", "status": "INTERNAL"}}"#,
+        )
+        .unwrap();
+        assert_eq!(detail.error.kind(), Some(ServerErrorKind::Internal));
+        let detail: ErrorPayload = serde_json::from_str(
+            r#"{"error": {"type": "invalid_request_error", "code": "session_expired", "message": "Your session hit the maximum duration"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            detail.error.into_error(),
+            GeminiError::Server(ServerError { kind: ServerErrorKind::SessionExpired, .. })
+        ));
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let server = |kind| {
+            GeminiError::Server(ServerError {
+                kind,
+                message: String::new(),
+            })
+        };
+        assert_eq!(server(ServerErrorKind::InvalidArgument).retry(), Retry::Fatal);
+        assert_eq!(server(ServerErrorKind::QuotaExhausted).retry(), Retry::Fatal);
+        assert_eq!(server(ServerErrorKind::RateLimited).retry(), Retry::Backoff);
+        assert_eq!(server(ServerErrorKind::ModelNotFound).retry(), Retry::Fatal);
+        assert_eq!(server(ServerErrorKind::SessionExpired).retry(), Retry::Fresh);
+        assert_eq!(server(ServerErrorKind::Internal).retry(), Retry::Resume);
+        assert_eq!(GeminiError::Auth("denied".into()).retry(), Retry::Fatal);
+        assert_eq!(GeminiError::ConnectionClosed.retry(), Retry::Resume);
+        assert_eq!(GeminiError::Timeout.retry(), Retry::Resume);
+    }
+
+    #[test]
+    fn test_rate_limited_handshake_is_retryable() {
+        let response = |status: u16, body: &str| {
+            let response = tokio_tungstenite::tungstenite::http::Response::builder()
+                .status(status)
+                .body(Some(body.as_bytes().to_vec()))
+                .unwrap();
+            GeminiError::from_handshake(WsError::Http(response))
+        };
+
+        let error = response(429, "");
+        assert!(matches!(
+            &error,
+            GeminiError::Server(ServerError { kind: ServerErrorKind::RateLimited, .. })
+        ));
+        assert_eq!(error.retry(), Retry::Backoff);
+
+        let error = response(
+            429,
+            r#"{"error": {"code": 429, "message": "Quota exceeded", "status": "RESOURCE_EXHAUSTED"}}"#,
+        );
+        assert_eq!(error.retry(), Retry::Backoff);
+        assert_eq!(error.to_string(), "Rate limited: Quota exceeded");
+
+        assert!(matches!(response(401, ""), GeminiError::Auth(_)));
+        assert_eq!(response(404, "").retry(), Retry::Fatal);
+    }
+}
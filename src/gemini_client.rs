@@ -4,21 +4,28 @@
 //! a split sink/stream approach for concurrent reading and writing.
 
 use crate::gemini::{
-    ApiResponse, BidiGenerateContentSetup, ClientMessage, Content, GeminiClientConfig, GeminiError,
-    GenerationConfig, Part, RealtimeAudio, RealtimeInput, RealtimeVideo, Result, ServerMessage,
-    Transcript,
+    parse_duration, ActivityHandling, ApiResponse, AudioTranscriptionConfig,
+    AutomaticActivityDetection, Backend, BidiGenerateContentSetup, ClientContent, ClientMessage,
+    CodeExecution, Content, GeminiClientConfig, GeminiError, GenerationConfig, GoogleSearch, Part, RealtimeAudio, RealtimeInput,
+    RealtimeInputConfig, RealtimeVideo, ResponseModality, Result, Retry, ServerContent, ServerError, ServerMessage,
+    SessionResumptionConfig, ToolDeclaration, TurnCoverage, UsageMetadata, MAX_RECONNECT_DELAY,
 };
+use crate::auth::{redact_url, ApiKey, AuthProvider, Credential};
+use crate::media_event::WsOutbound;
+use crate::wire_trace::{ConnectionTrace, WireTracer};
 
 use base64::engine::general_purpose;
 use base64::Engine; // Add this trait to use encode/decode methods
 use futures_util::{SinkExt, StreamExt};
 use tokio::sync::{mpsc, Mutex};
 use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
+use std::collections::VecDeque;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// Type alias for the WebSocket split sink, wrapped in Arc<Mutex<>>
 type WsSink = Arc<
@@ -37,6 +44,15 @@ type WsStream = futures_util::stream::SplitStream<
     tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>,
 >;
 
+/// Latest session resumption handle, shared with the inbound task
+type SessionHandle = Arc<std::sync::Mutex<Option<String>>>;
+
+/// Maximum number of outbound messages held back while reconnecting
+const MAX_BUFFERED_MESSAGES: usize = 512;
+
+/// Longest a replaced session may take to finish its last response
+const MAX_DRAIN_TIME: Duration = Duration::from_secs(30);
+
 /// Connection state of the Gemini client
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ConnectionState {
@@ -45,48 +61,238 @@ enum ConnectionState {
     SetupComplete,
 }
 
+/// Connection-level events reported by the inbound task of the current socket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// The server acknowledged our setup message
+    SetupComplete,
+    /// The server announced it will disconnect soon
+    GoAway(Option<Duration>),
+    /// The socket was closed or errored
+    Closed,
+    /// The server closed the socket because of an error
+    Failed(ServerError),
+    /// Pings or activityEnd went unanswered, the socket is probably half-open
+    Unresponsive,
+}
+
+/// Liveness and turn state of a socket, shared by its inbound and keepalive tasks
+#[derive(Debug, Clone, Copy)]
+struct Health {
+    /// Last frame of any kind from the server, pongs included
+    last_frame: Instant,
+    /// Set when activityEnd is sent, cleared by the next server message
+    awaiting_response_since: Option<Instant>,
+    /// Set when activityEnd is sent, cleared by turnComplete
+    response_pending: bool,
+    /// Replaced by a standby session; closing is expected and not reported
+    draining: bool,
+}
+
+impl Health {
+    fn new(now: Instant) -> Self {
+        Self {
+            last_frame: now,
+            awaiting_response_since: None,
+            response_pending: false,
+            draining: false,
+        }
+    }
+
+    fn on_frame(&mut self, now: Instant, is_message: bool) {
+        self.last_frame = now;
+        if is_message {
+            self.awaiting_response_since = None;
+        }
+    }
+
+    /// Why the connection should be given up on, if it should
+    fn check(&self, now: Instant, config: &GeminiClientConfig) -> Option<&'static str> {
+        if let Some(interval) = config.keepalive_interval {
+            if now.duration_since(self.last_frame) > interval + config.pong_timeout {
+                return Some("no pong from server");
+            }
+        }
+        match (config.response_timeout, self.awaiting_response_since) {
+            (Some(timeout), Some(since)) if now.duration_since(since) > timeout => {
+                Some("no server message after activityEnd")
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Redesigned Gemini Live API client with split WebSocket handling
 pub struct GeminiClient {
     config: GeminiClientConfig,
+    auth: Option<Arc<dyn AuthProvider>>,
     state: ConnectionState,
-    session_token: Option<String>,
+    session_token: SessionHandle,
+    health: Arc<std::sync::Mutex<Health>>,
 
     // Direct reference to the WebSocket write half for sending messages
     ws_writer: Option<WsSink>,
 
-    // Channel for receiving messages from the WebSocket
+    // Channel for receiving messages from the WebSocket. The sender outlives
+    // individual connections so subscribers keep receiving across reconnects.
+    response_tx: mpsc::Sender<Result<ApiResponse>>,
     response_rx: mpsc::Receiver<Result<ApiResponse>>,
 
+    // Connection events from the inbound task of the current socket
+    events_rx: mpsc::UnboundedReceiver<ConnectionEvent>,
+
+    // Outbound messages that could not be sent while the connection was down
+    pending: VecDeque<ClientMessage>,
+
     // Task handles to keep background tasks alive
     _rx_task: Option<JoinHandle<()>>,
     _tx_task: Option<JoinHandle<()>>,
+    keepalive_task: Option<JoinHandle<()>>,
+
+    // When the current socket was opened and the handle its session resumed from
+    connected_at: Instant,
+    resumed_from: Option<String>,
+
+    // Session set up ahead of a GoAway, taken over at the next turn boundary
+    standby: Option<Connection>,
+    // Between activityStart and activityEnd
+    in_turn: bool,
+
+    // Records every frame of every connection if set
+    tracer: Option<WireTracer>,
+    trace: Option<ConnectionTrace>,
+}
+
+/// A socket with its tasks, detached from the client while a standby session
+/// is prepared or a replaced session finishes its last response
+struct Connection {
+    state: ConnectionState,
+    session_token: SessionHandle,
+    health: Arc<std::sync::Mutex<Health>>,
+    ws_writer: Option<WsSink>,
+    events_rx: mpsc::UnboundedReceiver<ConnectionEvent>,
+    rx_task: Option<JoinHandle<()>>,
+    keepalive_task: Option<JoinHandle<()>>,
+    connected_at: Instant,
+    resumed_from: Option<String>,
+    trace: Option<ConnectionTrace>,
+}
+
+impl Connection {
+    /// Not connected yet; the session will resume from `handle`
+    fn disconnected(handle: Option<String>) -> Self {
+        let (_, events_rx) = mpsc::unbounded_channel();
+        Self {
+            state: ConnectionState::Disconnected,
+            session_token: Arc::new(std::sync::Mutex::new(handle)),
+            health: Arc::new(std::sync::Mutex::new(Health::new(Instant::now()))),
+            ws_writer: None,
+            events_rx,
+            rx_task: None,
+            keepalive_task: None,
+            connected_at: Instant::now(),
+            resumed_from: None,
+            trace: None,
+        }
+    }
+
+    /// Close the socket without notifying subscribers.
+    async fn close(mut self) {
+        // Abort the inbound task first so a deliberate close is not reported as a drop
+        if let Some(task) = self.rx_task.take() {
+            task.abort();
+        }
+        if let Some(task) = self.keepalive_task.take() {
+            task.abort();
+        }
+
+        if let Some(writer) = self.ws_writer.take() {
+            if let Some(trace) = &self.trace {
+                trace.sent(&Message::Close(None));
+            }
+            let mut writer_guard = writer.lock().await;
+            if tokio::time::timeout(Duration::from_secs(1), writer_guard.close())
+                .await
+                .is_err()
+            {
+                debug!("Timed out closing old WebSocket");
+            }
+        }
+    }
+
+    /// Let the session finish the response in flight, then close it quietly.
+    fn drain(self) {
+        let health = self.health.clone();
+        health.lock().unwrap().draining = true;
+        tokio::spawn(async move {
+            let finished = tokio::time::timeout(MAX_DRAIN_TIME, async {
+                while health.lock().unwrap().response_pending {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+            })
+            .await;
+            if finished.is_err() {
+                warn!("Replaced session did not finish its response in time");
+            }
+            self.close().await;
+            info!("Closed replaced Gemini session");
+        });
+    }
 }
 
 impl GeminiClient {
     /// Create a new Gemini client with the given configuration.
     pub fn new(config: GeminiClientConfig) -> Self {
+        let (response_tx, response_rx) = mpsc::channel(100);
         // Create dummy channel until connect() is called
-        let (_, response_rx) = mpsc::channel(100);
+        let (_, events_rx) = mpsc::unbounded_channel();
 
         Self {
             config,
+            auth: None,
             state: ConnectionState::Disconnected,
-            session_token: None,
+            session_token: Arc::new(std::sync::Mutex::new(None)),
+            health: Arc::new(std::sync::Mutex::new(Health::new(Instant::now()))),
             ws_writer: None,
+            response_tx,
             response_rx,
+            events_rx,
+            pending: VecDeque::new(),
             _rx_task: None,
             _tx_task: None,
+            keepalive_task: None,
+            connected_at: Instant::now(),
+            resumed_from: None,
+            standby: None,
+            in_turn: false,
+            tracer: None,
+            trace: None,
         }
     }
 
     /// Create a new Gemini client from an API key and optional configuration.
+    ///
+    /// The key is sent in a header, never in the URL.
     pub fn from_api_key(api_key: &str, config: Option<GeminiClientConfig>) -> Self {
-        let mut config = config.unwrap_or_default();
-        config.url = format!(
-            "wss://generativelanguage.googleapis.com/ws/google.ai.generativelanguage.v1beta.GenerativeService.BidiGenerateContent?key={}",
-            api_key
-        );
-        Self::new(config)
+        Self::with_auth(Arc::new(ApiKey(api_key.to_string())), config)
+    }
+
+    /// Create a new Gemini client that asks `auth` for credentials on every connect.
+    ///
+    /// If the configuration has no URL, the endpoint matching the credential is used.
+    pub fn with_auth(auth: Arc<dyn AuthProvider>, config: Option<GeminiClientConfig>) -> Self {
+        let mut client = Self::new(config.unwrap_or_default());
+        client.auth = Some(auth);
+        client
+    }
+
+    /// Write every frame from the next connection on to `tracer`
+    pub fn set_tracer(&mut self, tracer: WireTracer) {
+        self.tracer = Some(tracer);
+    }
+
+    pub fn config(&self) -> &GeminiClientConfig {
+        &self.config
     }
 
     /// Get a receiver to subscribe to responses without holding the client mutex
@@ -111,14 +317,51 @@ impl GeminiClient {
             return Ok(());
         }
 
-        info!("Connecting to Gemini API at {}", self.config.url);
+        // Fetch fresh credentials for every connection so expiring tokens are renewed
+        let credential = match &self.auth {
+            Some(auth) => Some(
+                auth.credential()
+                    .await
+                    .map_err(|e| GeminiError::Auth(format!("{:#}", e)))?,
+            ),
+            None => None,
+        };
+
+        // Vertex AI only accepts OAuth access tokens
+        if let (Backend::VertexAi { .. }, Some(credential)) = (&self.config.backend, &credential) {
+            if !matches!(credential, Credential::Bearer(_)) {
+                return Err(GeminiError::Auth(format!(
+                    "Vertex AI needs a bearer token, got {:?}",
+                    credential
+                )));
+            }
+        }
+
+        let url = if !self.config.url.is_empty() {
+            self.config.url.clone()
+        } else if let Some(url) = self.config.backend.url() {
+            url
+        } else if let Some(credential) = &credential {
+            credential.default_url().to_string()
+        } else {
+            crate::auth::LIVE_API_URL.to_string()
+        };
+        info!("Connecting to Gemini API at {}", redact_url(&url));
+
+        let mut request = url.as_str().into_client_request()?;
+        if let Some(credential) = &credential {
+            credential
+                .apply(&mut request)
+                .map_err(|e| GeminiError::Auth(e.to_string()))?;
+        }
 
         // Connect to the WebSocket
-        let (ws_stream, resp) = connect_async(&self.config.url)
+        let (ws_stream, resp) = connect_async(request)
             .await
-            .map_err(GeminiError::WebSocket)?;
+            .map_err(GeminiError::from_handshake)?;
 
         debug!("WebSocket connection response: {:?}", resp);
+        self.trace = self.tracer.as_ref().map(|tracer| tracer.connection(&url));
 
         // Split the WebSocket into separate sink (write) and stream (read) halves
         let (sink, stream) = ws_stream.split();
@@ -130,7 +373,13 @@ impl GeminiClient {
         self.ws_writer = Some(sink_shared.clone());
 
         // ------ Set up the inbound message channel ------
-        let (response_tx, new_response_rx) = mpsc::channel::<Result<ApiResponse>>(100);
+        let response_tx = self.response_tx.clone();
+        let (events_tx, events_rx) = mpsc::unbounded_channel::<ConnectionEvent>();
+        let events_tx_keepalive = events_tx.clone();
+        let session_token = self.session_token.clone();
+        self.health = Arc::new(std::sync::Mutex::new(Health::new(Instant::now())));
+        let health = self.health.clone();
+        let trace = self.trace.clone();
 
         // Spawn a task to handle inbound messages
         let rx_task = tokio::spawn(async move {
@@ -138,8 +387,19 @@ impl GeminiClient {
 
             // Process incoming messages from the WebSocket
             let mut stream = stream;
+            let mut context = ContextTracker::default();
+            // Error reported in the server's close frame
+            let mut failure = None;
 
             while let Some(message_result) = stream.next().await {
+                if let Ok(message) = &message_result {
+                    if let Some(trace) = &trace {
+                        trace.received(message);
+                    }
+                    let is_message = matches!(message, Message::Text(_) | Message::Binary(_));
+                    health.lock().unwrap().on_frame(Instant::now(), is_message);
+                }
+
                 match message_result {
                     Ok(Message::Text(text)) => {
                         crate::tdbg!("⬅ websocket message received");
@@ -148,77 +408,18 @@ impl GeminiClient {
                         // Parse and handle the server message
                         match serde_json::from_str::<ServerMessage>(&text) {
                             Ok(server_message) => {
-                                // Handle the server message based on its type
-                                match server_message {
-                                    ServerMessage::SetupComplete { .. } => {
-                                        if let Err(_) =
-                                            response_tx.send(Ok(ApiResponse::SetupComplete)).await
-                                        {
-                                            error!("Failed to send SetupComplete response");
-                                            break;
-                                        }
-                                    }
-                                    ServerMessage::ServerContent { server_content } => {
-                                        // Process model content, transcriptions, etc.
-                                        if let Err(_) =
-                                            handle_server_content(server_content, &response_tx)
-                                                .await
-                                        {
-                                            error!("Failed to handle server content");
-                                            break;
-                                        }
-                                    }
-                                    ServerMessage::ToolCall { tool_call } => {
-                                        if let Err(_) = response_tx
-                                            .send(Ok(ApiResponse::ToolCall(tool_call)))
-                                            .await
-                                        {
-                                            error!("Failed to send ToolCall response");
-                                            break;
-                                        }
-                                    }
-                                    ServerMessage::ToolCallCancellation {
-                                        tool_call_cancellation,
-                                    } => {
-                                        let id = tool_call_cancellation["id"]
-                                            .as_str()
-                                            .unwrap_or("unknown")
-                                            .to_string();
-
-                                        if let Err(_) = response_tx
-                                            .send(Ok(ApiResponse::ToolCallCancellation(id)))
-                                            .await
-                                        {
-                                            error!("Failed to send ToolCallCancellation response");
-                                            break;
-                                        }
-                                    }
-                                    ServerMessage::GoAway { .. } => {
-                                        if let Err(_) =
-                                            response_tx.send(Ok(ApiResponse::GoAway)).await
-                                        {
-                                            error!("Failed to send GoAway response");
-                                            break;
-                                        }
-                                    }
-                                    ServerMessage::SessionResumptionUpdate {
-                                        session_resumption_update,
-                                    } => {
-                                        let handle = session_resumption_update["newHandle"]
-                                            .as_str()
-                                            .unwrap_or("")
-                                            .to_string();
-
-                                        if let Err(_) = response_tx
-                                            .send(Ok(ApiResponse::SessionResumptionUpdate(handle)))
-                                            .await
-                                        {
-                                            error!(
-                                                "Failed to send SessionResumptionUpdate response"
-                                            );
-                                            break;
-                                        }
-                                    }
+                                if dispatch_server_message(
+                                    server_message,
+                                    &response_tx,
+                                    &events_tx,
+                                    &session_token,
+                                    &health,
+                                    &mut context,
+                                )
+                                .await
+                                .is_err()
+                                {
+                                    break;
                                 }
                                 crate::tdbg!("✅ websocket message processed");
                             }
@@ -245,79 +446,18 @@ impl GeminiClient {
                             // Try to parse it as a ServerMessage - binary messages can be valid responses
                             match serde_json::from_str::<ServerMessage>(&text) {
                                 Ok(server_message) => {
-                                    // Handle the server message based on its type
-                                    match server_message {
-                                        ServerMessage::SetupComplete { .. } => {
-                                            if let Err(_) = response_tx
-                                                .send(Ok(ApiResponse::SetupComplete))
-                                                .await
-                                            {
-                                                error!("Failed to send SetupComplete response");
-                                                break;
-                                            }
-                                        }
-                                        ServerMessage::ServerContent { server_content } => {
-                                            if let Err(_) =
-                                                handle_server_content(server_content, &response_tx)
-                                                    .await
-                                            {
-                                                error!("Failed to handle server content");
-                                                break;
-                                            }
-                                        }
-                                        ServerMessage::ToolCall { tool_call } => {
-                                            if let Err(_) = response_tx
-                                                .send(Ok(ApiResponse::ToolCall(tool_call)))
-                                                .await
-                                            {
-                                                error!("Failed to send ToolCall response");
-                                                break;
-                                            }
-                                        }
-                                        ServerMessage::ToolCallCancellation {
-                                            tool_call_cancellation,
-                                        } => {
-                                            let id = tool_call_cancellation["id"]
-                                                .as_str()
-                                                .unwrap_or("unknown")
-                                                .to_string();
-
-                                            if let Err(_) = response_tx
-                                                .send(Ok(ApiResponse::ToolCallCancellation(id)))
-                                                .await
-                                            {
-                                                error!(
-                                                    "Failed to send ToolCallCancellation response"
-                                                );
-                                                break;
-                                            }
-                                        }
-                                        ServerMessage::GoAway { .. } => {
-                                            if let Err(_) =
-                                                response_tx.send(Ok(ApiResponse::GoAway)).await
-                                            {
-                                                error!("Failed to send GoAway response");
-                                                break;
-                                            }
-                                        }
-                                        ServerMessage::SessionResumptionUpdate {
-                                            session_resumption_update,
-                                        } => {
-                                            let handle = session_resumption_update["newHandle"]
-                                                .as_str()
-                                                .unwrap_or("")
-                                                .to_string();
-
-                                            if let Err(_) = response_tx
-                                                .send(Ok(ApiResponse::SessionResumptionUpdate(
-                                                    handle,
-                                                )))
-                                                .await
-                                            {
-                                                error!("Failed to send SessionResumptionUpdate response");
-                                                break;
-                                            }
-                                        }
+                                    if dispatch_server_message(
+                                        server_message,
+                                        &response_tx,
+                                        &events_tx,
+                                        &session_token,
+                                        &health,
+                                        &mut context,
+                                    )
+                                    .await
+                                    .is_err()
+                                    {
+                                        break;
                                     }
                                     crate::tdbg!("✅ websocket binary message processed");
                                 }
@@ -362,8 +502,27 @@ impl GeminiClient {
                             info!("WebSocket closed without details");
                         }
 
+                        // A replaced session closing is expected
+                        if health.lock().unwrap().draining {
+                            break;
+                        }
+
+                        failure = frame.as_ref().and_then(|frame| {
+                            ServerError::from_close(u16::from(frame.code), &frame.reason)
+                        });
+                        let error = match &failure {
+                            Some(failure) => {
+                                if failure.kind.retry() == Retry::Fresh {
+                                    // Resuming would fail the same way
+                                    *session_token.lock().unwrap() = None;
+                                }
+                                GeminiError::Server(failure.clone())
+                            }
+                            None => GeminiError::ConnectionClosed,
+                        };
+
                         // Notify that the connection is closed (for error handling)
-                        if let Err(_) = response_tx.send(Err(GeminiError::ConnectionClosed)).await {
+                        if let Err(_) = response_tx.send(Err(error)).await {
                             error!("Failed to send connection closed notification");
                         }
 
@@ -381,6 +540,10 @@ impl GeminiClient {
                     Err(e) => {
                         error!("WebSocket error: {:?}", e);
 
+                        if health.lock().unwrap().draining {
+                            break;
+                        }
+
                         if let Err(_) = response_tx.send(Err(GeminiError::WebSocket(e))).await {
                             error!("Failed to send WebSocket error");
                         }
@@ -390,20 +553,254 @@ impl GeminiClient {
                 }
             }
 
+            // Let the owner of the client know this socket is gone so it can reconnect
+            let _ = events_tx.send(match failure {
+                Some(failure) => ConnectionEvent::Failed(failure),
+                None => ConnectionEvent::Closed,
+            });
+
             info!("Inbound message task terminated");
         });
 
-        // Store the response channel and task handles in the client
-        self.response_rx = new_response_rx;
+        // Store the event channel and task handles in the client
+        self.keepalive_task = Some(tokio::spawn(keepalive(
+            sink_shared,
+            self.health.clone(),
+            self.config.clone(),
+            events_tx_keepalive,
+            self.trace.clone(),
+        )));
+        self.events_rx = events_rx;
         self._rx_task = Some(rx_task);
 
         // Update the client state
+        self.connected_at = Instant::now();
         self.state = ConnectionState::Connected;
         info!("Connected to Gemini API");
 
         Ok(())
     }
 
+    /// Close the current socket (if any) without notifying subscribers.
+    async fn close_connection(&mut self) {
+        let handle = self.session_token.lock().unwrap().clone();
+        self.swap_connection(Connection::disconnected(handle)).close().await;
+    }
+
+    /// Put `connection` in place of the current one and return the current one.
+    fn swap_connection(&mut self, connection: Connection) -> Connection {
+        use std::mem::replace;
+        Connection {
+            state: replace(&mut self.state, connection.state),
+            session_token: replace(&mut self.session_token, connection.session_token),
+            health: replace(&mut self.health, connection.health),
+            ws_writer: replace(&mut self.ws_writer, connection.ws_writer),
+            events_rx: replace(&mut self.events_rx, connection.events_rx),
+            rx_task: replace(&mut self._rx_task, connection.rx_task),
+            keepalive_task: replace(&mut self.keepalive_task, connection.keepalive_task),
+            connected_at: replace(&mut self.connected_at, connection.connected_at),
+            resumed_from: replace(&mut self.resumed_from, connection.resumed_from),
+            trace: replace(&mut self.trace, connection.trace),
+        }
+    }
+
+    /// Open and set up a second session next to the current one, resuming
+    /// from its latest handle. Outbound messages move to the new session at
+    /// the next turn boundary; the old one is closed once its response is done.
+    ///
+    /// Falls back to a plain reconnect if there is no live session to hand over from.
+    pub async fn prepare_standby(&mut self) -> Result<()> {
+        if self.state != ConnectionState::SetupComplete {
+            return self.reconnect().await;
+        }
+
+        let handle = self.session_token.lock().unwrap().clone();
+        if let Some(standby) = self.standby.take() {
+            if standby.resumed_from == handle {
+                self.standby = Some(standby);
+                return Ok(());
+            }
+            // The session moved on since the standby resumed it
+            standby.close().await;
+        }
+
+        info!("Preparing standby Gemini session (resuming: {})", handle.is_some());
+        let primary = self.swap_connection(Connection::disconnected(handle));
+        let result = self.connect_and_setup().await;
+        let standby = self.swap_connection(primary);
+        match result {
+            Ok(()) => {
+                info!("Standby Gemini session ready");
+                self.standby = Some(standby);
+                if self.at_turn_boundary() {
+                    self.promote_standby().await?;
+                }
+                Ok(())
+            }
+            Err(e) => {
+                standby.close().await;
+                Err(e)
+            }
+        }
+    }
+
+    /// No user turn open and no response outstanding on the current session
+    fn at_turn_boundary(&self) -> bool {
+        !self.in_turn && !self.health.lock().unwrap().response_pending
+    }
+
+    /// Switch to the standby session and let the current one drain.
+    async fn promote_standby(&mut self) -> Result<()> {
+        // A turn finished after the standby was set up; resume from the newer state
+        let handle = self.session_token.lock().unwrap().clone();
+        if self.standby.as_ref().is_some_and(|s| s.resumed_from != handle) {
+            info!("Session moved on since the standby was set up, preparing a new one");
+            if let Some(stale) = self.standby.take() {
+                stale.close().await;
+            }
+            let primary = self.swap_connection(Connection::disconnected(handle));
+            let result = self.connect_and_setup().await;
+            let standby = self.swap_connection(primary);
+            if let Err(e) = result {
+                standby.close().await;
+                return Err(e);
+            }
+            self.standby = Some(standby);
+        }
+
+        let Some(standby) = self.standby.take() else {
+            return Ok(());
+        };
+        info!("Handing over to standby Gemini session");
+        let old = self.swap_connection(standby);
+        if old.state == ConnectionState::Disconnected {
+            old.close().await;
+        } else {
+            old.drain();
+        }
+        self.flush_pending().await
+    }
+
+    /// Wait until the current connection is lost or the server announces a
+    /// GoAway. A GoAway is also reported once the connection gets close to
+    /// `connection_lifetime`.
+    ///
+    /// Returns immediately with `Closed` if there is no live connection.
+    pub async fn connection_lost(&mut self) -> ConnectionEvent {
+        // Rotate ahead of the server's limit unless a standby is already waiting
+        let rotate_at = match (self.config.connection_lifetime, &self.standby) {
+            (Some(lifetime), None) => Some(self.connected_at + lifetime),
+            _ => None,
+        };
+
+        loop {
+            let event = tokio::select! {
+                event = self.events_rx.recv() => event,
+                _ = sleep_until(rotate_at), if rotate_at.is_some() => {
+                    info!("Connection lifetime almost over, rotating session");
+                    // Ask again one lifetime later if the handover does not happen
+                    self.connected_at = Instant::now();
+                    return ConnectionEvent::GoAway(None);
+                }
+            };
+            match event {
+                Some(ConnectionEvent::SetupComplete) => continue,
+                Some(ConnectionEvent::GoAway(time_left)) => {
+                    return ConnectionEvent::GoAway(time_left)
+                }
+                Some(ConnectionEvent::Unresponsive) => {
+                    self.state = ConnectionState::Disconnected;
+                    return ConnectionEvent::Unresponsive;
+                }
+                Some(ConnectionEvent::Failed(error)) => {
+                    self.state = ConnectionState::Disconnected;
+                    return ConnectionEvent::Failed(error);
+                }
+                Some(ConnectionEvent::Closed) | None => {
+                    // Buffer anything sent from now on instead of writing to a dead socket
+                    self.state = ConnectionState::Disconnected;
+                    return ConnectionEvent::Closed;
+                }
+            }
+        }
+    }
+
+    /// Tear down the current socket and establish a new session, resuming the
+    /// previous one when a resumption handle is available.
+    ///
+    /// Retries up to `reconnect_attempts` times with exponential backoff starting
+    /// at `reconnect_delay`, unless an attempt fails with an error that
+    /// retrying cannot fix. Realtime input buffered while disconnected is sent
+    /// once the new session is set up.
+    pub async fn reconnect(&mut self) -> Result<()> {
+        // A warm standby takes over right away
+        if self.standby.is_some() {
+            self.in_turn = false;
+            match self.promote_standby().await {
+                Ok(()) => return Ok(()),
+                Err(e) => warn!("Standby session unusable, reconnecting: {}", e),
+            }
+        }
+
+        self.close_connection().await;
+
+        let mut delay = self.config.reconnect_delay;
+        let mut last_error = GeminiError::ConnectionClosed;
+
+        for attempt in 1..=self.config.reconnect_attempts {
+            info!(
+                "Reconnecting to Gemini (attempt {}/{}, resuming: {})",
+                attempt,
+                self.config.reconnect_attempts,
+                self.session_token.lock().unwrap().is_some()
+            );
+
+            match self.connect_and_setup().await {
+                Ok(()) => {
+                    info!("Reconnected to Gemini");
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!("Reconnect attempt {} failed: {}", attempt, e);
+                    self.close_connection().await;
+                    match e.retry() {
+                        Retry::Fatal => {
+                            error!("Not retrying Gemini reconnect: {}", e);
+                            return Err(e);
+                        }
+                        Retry::Fresh => {
+                            info!("Session cannot be resumed, starting a new one");
+                            *self.session_token.lock().unwrap() = None;
+                        }
+                        Retry::Backoff => delay = MAX_RECONNECT_DELAY,
+                        Retry::Resume => {}
+                    }
+                    last_error = e;
+                    if attempt < self.config.reconnect_attempts {
+                        tokio::time::sleep(delay).await;
+                        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    }
+                }
+            }
+        }
+
+        error!("Giving up on Gemini reconnect");
+        Err(last_error)
+    }
+
+    /// Switch to a new configuration by reconnecting with a new setup message.
+    ///
+    /// The session is resumed if possible, so the conversation carries over;
+    /// the model cannot change on resumption.
+    pub async fn reconfigure(&mut self, config: GeminiClientConfig) -> Result<()> {
+        // A standby was set up with the old configuration
+        if let Some(standby) = self.standby.take() {
+            standby.close().await;
+        }
+        self.config = config;
+        self.reconnect().await
+    }
+
     /// Initialize a session by sending the setup message.
     pub async fn setup(&mut self) -> Result<()> {
         if self.state == ConnectionState::Disconnected {
@@ -420,13 +817,14 @@ impl GeminiClient {
 
         // Create the setup message
         let mut setup = BidiGenerateContentSetup {
-            model: self.config.model.clone(),
+            model: self.config.backend.model_path(&self.config.model),
             // Convert the system instruction to the proper Content format if provided
             system_instruction: self.config.system_instruction.as_ref().map(|instruction| {
                 Content {
                     role: Some("SYSTEM".to_string()),
                     parts: vec![Part {
                         text: Some(instruction.clone()),
+                        ..Default::default()
                     }],
                 }
             }),
@@ -445,49 +843,85 @@ impl GeminiClient {
             generation_config.media_resolution = Some(resolution.as_str().to_string());
         }
 
-        setup.generation_config = Some(generation_config);
+        // Voice and language only apply to spoken responses
+        if let Some(speech) = &self.config.speech {
+            if self.config.response_modality == ResponseModality::Audio {
+                generation_config.speech_config = Some(speech.clone());
+            } else {
+                warn!("Ignoring speech config, responses are text");
+            }
+        }
 
-        // Create realtime_input_config with correct fields for the Live API
-        let mut realtime_config = if let Some(token) = &self.session_token {
-            serde_json::json!({
-                "sessionResumptionConfig": {
-                    "handle": token
-                }
-            })
-        } else {
-            serde_json::json!({})
-        };
+        setup.generation_config = Some(generation_config);
 
-        // Configure for client-side VAD (since we're using Whisper-based segmentation)
-        let config_map = realtime_config.as_object_mut().unwrap();
+        // Declare tools the model may call, client-side functions and built-ins
+        let mut tools = self.config.tools.clone();
+        if self.config.code_execution {
+            tools.push(ToolDeclaration {
+                code_execution: Some(CodeExecution::default()),
+                ..Default::default()
+            });
+        }
+        if self.config.google_search {
+            tools.push(ToolDeclaration {
+                google_search: Some(GoogleSearch::default()),
+                ..Default::default()
+            });
+        }
+        if !tools.is_empty() {
+            setup.tools = Some(tools);
+        }
 
-        // Disable automatic activity detection since we're doing client-side VAD
-        config_map.insert(
-            "automaticActivityDetection".to_string(),
-            serde_json::json!({
-                "disabled": true
-            }),
-        );
+        // Enable session resumption so we can reconnect without losing context
+        if self.config.session_resumption {
+            setup.session_resumption = Some(SessionResumptionConfig {
+                handle: self.session_token.lock().unwrap().clone(),
+            });
+        }
 
-        // Set activity handling to NO_INTERRUPTION for natural batching
-        config_map.insert(
-            "activityHandling".to_string(),
-            serde_json::json!("NO_INTERRUPTION"),
-        );
+        // Transcripts show what the server actually heard and said
+        if self.config.input_transcription {
+            setup.input_audio_transcription = Some(AudioTranscriptionConfig::default());
+        }
+        if self.config.output_transcription
+            && self.config.response_modality == ResponseModality::Audio
+        {
+            setup.output_audio_transcription = Some(AudioTranscriptionConfig::default());
+        }
 
-        // Set turnCoverage to include all input (not just within activity markers)
-        config_map.insert(
-            "turnCoverage".to_string(),
-            serde_json::json!("TURN_INCLUDES_ONLY_ACTIVITY"),
-        );
+        // Let long sessions drop old context instead of hitting the limit
+        if let Some(compression) = &self.config.context_window_compression {
+            info!(
+                "Context window compression enabled (trigger: {:?}, target: {:?})",
+                compression.trigger_tokens,
+                compression.sliding_window.as_ref().and_then(|w| w.target_tokens)
+            );
+            setup.context_window_compression = Some(compression.clone());
+        }
 
-        setup.realtime_input_config = Some(realtime_config);
+        // Configure for client-side VAD (since we're using Whisper-based segmentation)
+        setup.realtime_input_config = Some(RealtimeInputConfig {
+            // Disable automatic activity detection since we're doing client-side VAD
+            automatic_activity_detection: Some(AutomaticActivityDetection {
+                disabled: Some(true),
+            }),
+            // Set activity handling to NO_INTERRUPTION for natural batching
+            activity_handling: Some(ActivityHandling::NoInterruption),
+            // Only include input between activity markers in the turn
+            turn_coverage: Some(TurnCoverage::TurnIncludesOnlyActivity),
+        });
 
         info!("Sending setup message with model: {}", setup.model);
 
-        // Send the setup message directly using our send method
+        // Setup is serialized like every other client message
+        self.resumed_from = setup
+            .session_resumption
+            .as_ref()
+            .and_then(|resumption| resumption.handle.clone());
+        let resuming = self.resumed_from.is_some();
+
         let msg = ClientMessage::Setup { setup };
-        if let Err(e) = self.send(&msg).await {
+        if let Err(e) = self.write_message(&msg).await {
             error!("Failed to send setup message: {:?}", e);
             return Err(e);
         }
@@ -506,7 +940,21 @@ impl GeminiClient {
         if setup_completed {
             self.state = ConnectionState::SetupComplete;
             info!("Gemini session setup complete");
-            Ok(())
+
+            // A resumed session already has the context in its history
+            if !resuming && !self.config.initial_context.is_empty() {
+                info!(
+                    "Seeding session with {} context turns",
+                    self.config.initial_context.len()
+                );
+                self.send_client_content(ClientContent {
+                    turns: self.config.initial_context.clone(),
+                    turn_complete: false,
+                })
+                .await?;
+            }
+
+            self.flush_pending().await
         } else {
             error!("Failed to complete Gemini session setup");
             Err(GeminiError::SetupNotComplete)
@@ -515,236 +963,40 @@ impl GeminiClient {
 
     /// Wait for the setup complete message.
     async fn wait_for_setup_complete(&mut self) -> Result<bool> {
-        let mut attempts = 0;
-        while attempts < 10 {
-            match self.response_rx.recv().await {
-                Some(Ok(ApiResponse::SetupComplete)) => {
-                    return Ok(true);
-                }
-                Some(Ok(_)) => {
-                    // Ignore other messages
-                    attempts += 1;
-                    continue;
-                }
-                Some(Err(e)) => {
-                    // Propagate any errors
-                    return Err(e);
-                }
-                None => {
-                    // Channel closed
-                    return Err(GeminiError::ChannelClosed);
-                }
-            }
+        match self.events_rx.recv().await {
+            Some(ConnectionEvent::SetupComplete) => Ok(true),
+            Some(ConnectionEvent::GoAway(_)) => Ok(false),
+            Some(ConnectionEvent::Unresponsive) => Err(GeminiError::Timeout),
+            Some(ConnectionEvent::Failed(error)) => Err(error.into()),
+            Some(ConnectionEvent::Closed) | None => Err(GeminiError::ConnectionClosed),
         }
-        Ok(false) // Timed out without seeing SetupComplete
     }
 
-    /// Send a client message to the server using the WebSocket writer.
-    pub async fn send(&mut self, msg: &ClientMessage) -> Result<()> {
-        // Check if connection is already closed or writer is cleared
-        if self.state == ConnectionState::Disconnected || self.ws_writer.is_none() {
-            error!("Cannot send message: Connection is closed");
-            return Err(GeminiError::ConnectionClosed);
+    /// Send outbound messages that were buffered while the connection was down.
+    async fn flush_pending(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
         }
 
-        // Format the JSON based on message type
-        let json = match msg {
-            ClientMessage::Setup { setup } => {
-                // Format the JSON manually to avoid nesting issues
-                let setup_json =
-                    serde_json::to_string(setup).map_err(GeminiError::Serialization)?;
-                // Remove outer braces and wrap in setup: {...}
-                let inner = &setup_json[1..setup_json.len() - 1];
-                format!("{{\"setup\":{{{}}}}}", inner)
-            }
-            ClientMessage::ClientContent { client_content } => {
-                format!(
-                    "{{\"clientContent\":{}}}",
-                    serde_json::to_string(client_content).map_err(GeminiError::Serialization)?
-                )
-            }
-            ClientMessage::RealtimeInput { realtime_input } => {
-                format!(
-                    "{{\"realtimeInput\":{}}}",
-                    serde_json::to_string(realtime_input).map_err(GeminiError::Serialization)?
-                )
-            }
-            ClientMessage::ToolResponse { tool_response } => {
-                format!(
-                    "{{\"toolResponse\":{}}}",
-                    serde_json::to_string(tool_response).map_err(GeminiError::Serialization)?
-                )
-            }
-        };
-
-        // Log message type without the full content to avoid spamming the console
-        match msg {
-            ClientMessage::Setup { .. } => info!("Sending setup message to Gemini API"),
-            ClientMessage::ClientContent { .. } => info!("Sending text content to Gemini API"),
-            ClientMessage::RealtimeInput { realtime_input } => {
-                if realtime_input.audio.is_some() {
-                    if realtime_input.audio_stream_end.unwrap_or(false) {
-                        info!("📤 Sending end-of-audio signal to Gemini API");
-                    } else {
-                        info!("📤 Sending audio chunk to Gemini API");
-                    }
-                } else if realtime_input.video.is_some() {
-                    info!("📤 Sending video frame to Gemini API");
-                } else if realtime_input.text.is_some() {
-                    info!("📤 Sending streaming text to Gemini API");
-                } else if realtime_input.activity_start.is_some() {
-                    info!("📤 Sending activityStart signal to Gemini API");
-                } else if realtime_input.activity_end.is_some() {
-                    info!("📤 Sending activityEnd signal to Gemini API");
-                }
+        info!("Flushing {} buffered outbound messages", self.pending.len());
+        while let Some(message) = self.pending.pop_front() {
+            if let Err(e) = self.write_message(&message).await {
+                // Keep it for the next attempt
+                self.pending.push_front(message);
+                return Err(e);
             }
-            ClientMessage::ToolResponse { .. } => info!("Sending tool response to Gemini API"),
-        };
-
-        // Use the WebSocket writer directly to send the message
-        if let Some(writer) = &self.ws_writer {
-            let mut writer_guard = writer.lock().await;
-            match writer_guard.send(Message::Text(json.into())).await {
-                Ok(_) => {
-                    debug!("Message sent successfully");
-                    Ok(())
-                }
-                Err(e) => {
-                    error!("Failed to send message: {:?}", e);
-
-                    // If we get a SendAfterClosing error, update our state
-                    if e.to_string().contains("SendAfterClosing") {
-                        error!("WebSocket is closed - will not try to send more messages");
-                        self.state = ConnectionState::Disconnected;
-                        // We'll clear the writer after the lock is released
-                        drop(writer_guard);
-                        self.ws_writer = None; // Prevent future send attempts
-                        return Err(GeminiError::WebSocket(e));
-                    }
-
-                    Err(GeminiError::WebSocket(e))
-                }
-            }
-        } else {
-            error!("WebSocket writer not available (not connected)");
-            Err(GeminiError::ConnectionClosed)
         }
-    }
-
-    /// Send an audio chunk to the server.
-    ///
-    /// * `audio_data` - Raw PCM audio bytes
-    /// * `activity_start` - Set to true to mark the beginning of speech (user started talking)
-    /// * `activity_end` - Set to true to mark the end of speech (user finished talking)
-    /// * `is_end` - Set to true to mark the end of the audio stream (used with auto VAD)
-    pub async fn send_audio(
-        &mut self,
-        audio_data: &[u8],
-        activity_start: bool,
-        activity_end: bool,
-        is_end: bool,
-    ) -> Result<()> {
-        // IMPORTANT: Use the improved send_audio_with_activity method that properly
-        // separates audio data and flags into different messages
-        self.send_audio_with_activity(audio_data, activity_start, activity_end, is_end)
-            .await
-    }
 
-    /// Send a video frame to the server.
-    pub async fn send_video(&mut self, frame_data: &[u8], mime_type: &str) -> Result<()> {
-        // Encode video data as base64
-        let data = general_purpose::STANDARD.encode(frame_data);
-
-        let realtime_input = RealtimeInput {
-            audio: None,
-            video: Some(RealtimeVideo {
-                data,
-                mime_type: mime_type.to_string(),
-            }),
-            text: None,
-            activity_start: None,
-            activity_end: None,
-            audio_stream_end: None,
-        };
-
-        let msg = ClientMessage::RealtimeInput { realtime_input };
-        self.send(&msg).await
-    }
-
-    /// Send a text message to the server.
-    pub async fn send_text(&mut self, text: &str) -> Result<()> {
-        let client_content = serde_json::json!({
-            "turns": [{
-                "role": "user",
-                "parts": [{
-                    "text": text
-                }]
-            }],
-            "turnComplete": true
-        });
-
-        let msg = ClientMessage::ClientContent { client_content };
-        self.send(&msg).await
+        Ok(())
     }
 
-    /// Send streaming text to the server (e.g. for partial typing).
-    pub async fn send_streaming_text(&mut self, text: &str) -> Result<()> {
-        let realtime_input = RealtimeInput {
-            audio: None,
-            video: None,
-            text: Some(text.to_string()),
-            activity_start: None,
-            activity_end: None,
-            audio_stream_end: None,
-        };
-
-        let msg = ClientMessage::RealtimeInput { realtime_input };
-        self.send(&msg).await
-    }
-    
-    /// Send a 20ms audio chunk for streaming (no activity flags)
-    pub async fn send_audio_chunk(&mut self, pcm_20ms: &[u8]) -> Result<()> {
-        let realtime_input = RealtimeInput {
-            audio: Some(RealtimeAudio {
-                data: general_purpose::STANDARD.encode(pcm_20ms),
-                mime_type: "audio/pcm;rate=16000".to_string(),
-            }),
-            video: None,
-            text: None,
-            activity_start: None,
-            activity_end: None,
-            audio_stream_end: None,
-        };
-        let msg = ClientMessage::RealtimeInput { realtime_input };
-        self.send(&msg).await
-    }
-    
-    /// Send activity start signal (no audio data)
-    pub async fn send_activity_start(&mut self) -> Result<()> {
-        let realtime_input = RealtimeInput {
-            audio: None,
-            video: None,
-            text: None,
-            activity_start: Some(serde_json::json!({})),
-            activity_end: None,
-            audio_stream_end: None,
-        };
-        let msg = ClientMessage::RealtimeInput { realtime_input };
-        self.send(&msg).await
-    }
-    
-    /// Send activity end signal (no audio data)
-    pub async fn send_activity_end(&mut self) -> Result<()> {
-        let realtime_input = RealtimeInput {
-            audio: None,
-            video: None,
-            text: None,
-            activity_start: None,
-            activity_end: Some(serde_json::json!({})),
-            audio_stream_end: None,
-        };
-        let msg = ClientMessage::RealtimeInput { realtime_input };
-        self.send(&msg).await
+    /// Hold an outbound message back until the session is re-established.
+    fn buffer_pending(&mut self, message: ClientMessage) {
+        if self.pending.len() >= MAX_BUFFERED_MESSAGES {
+            warn!("Reconnect buffer full, dropping oldest outbound message");
+            self.pending.pop_front();
+        }
+        self.pending.push_back(message);
     }
 
     /// Receive the next response from the server.
@@ -762,95 +1014,6 @@ impl GeminiClient {
         response
     }
 
-    /// Simplified helper for sending audio with clean parameter names
-    ///
-    /// When using with client-side VAD (automaticActivityDetection.disabled = true):
-    /// - Set activity_start=true to mark the beginning of a user turn
-    /// - Send multiple audio chunks with activity_start/end=false
-    /// - Set activity_end=true to mark the end of a user turn
-    /// - Set audio_stream_end=true when finished with entire session
-    ///
-    /// CRITICAL: Never mix audio data with activity flags in the same frame
-    /// The API treats all fields in RealtimeInput as a "oneof" union
-    pub async fn send_audio_with_activity(
-        &mut self,
-        audio_data: &[u8],
-        activity_start: bool,
-        activity_end: bool,
-        audio_stream_end: bool,
-    ) -> Result<()> {
-        // Log what we're doing to help with debugging
-        if cfg!(debug_assertions) {
-            debug!(
-                "send_audio_with_activity: data_len={}, start={}, end={}, stream_end={}",
-                audio_data.len(),
-                activity_start,
-                activity_end,
-                audio_stream_end
-            );
-        }
-
-        // Send activity_start flag in its own frame if requested
-        if activity_start {
-            let flag_only = RealtimeInput {
-                audio: None,
-                video: None,
-                text: None,
-                activity_start: Some(serde_json::json!({})),
-                activity_end: None,
-                audio_stream_end: None,
-            };
-            let msg = ClientMessage::RealtimeInput {
-                realtime_input: flag_only,
-            };
-            self.send(&msg).await?;
-        }
-
-        // If we have audio data, send it in its own clean frame (NO FLAGS)
-        if !audio_data.is_empty() {
-            // Send audio data only (no flags) in a clean frame
-            let data = general_purpose::STANDARD.encode(audio_data);
-            let audio_only = RealtimeInput {
-                audio: Some(RealtimeAudio {
-                    data,
-                    mime_type: "audio/pcm;rate=16000".to_string(),
-                }),
-                video: None,
-                text: None,
-                activity_start: None,   // IMPORTANT: No flags with audio data
-                activity_end: None,     // IMPORTANT: No flags with audio data
-                audio_stream_end: None, // IMPORTANT: No flags with audio data
-            };
-
-            let msg = ClientMessage::RealtimeInput {
-                realtime_input: audio_only,
-            };
-            self.send(&msg).await?;
-        }
-
-        // Send activity_end or audio_stream_end flags in their own frame if requested
-        if activity_end || audio_stream_end {
-            let flag_only = RealtimeInput {
-                audio: None,
-                video: None,
-                text: None,
-                activity_start: None,
-                activity_end: if activity_end {
-                    Some(serde_json::json!({}))
-                } else {
-                    None
-                },
-                audio_stream_end: if audio_stream_end { Some(true) } else { None },
-            };
-            let msg = ClientMessage::RealtimeInput {
-                realtime_input: flag_only,
-            };
-            self.send(&msg).await?;
-        }
-
-        Ok(())
-    }
-
     /// Stream responses until a condition is met.
     pub async fn stream_responses<F>(&mut self, mut callback: F) -> Result<()>
     where
@@ -887,7 +1050,7 @@ impl GeminiClient {
 
     /// Store a session resumption token for later reconnection.
     pub fn set_session_token(&mut self, token: String) {
-        self.session_token = Some(token);
+        *self.session_token.lock().unwrap() = Some(token);
     }
 
     /// Get the current connection state.
@@ -899,214 +1062,897 @@ impl GeminiClient {
         }
     }
 
-    /// Send raw realtime input JSON (for channel-based architecture)
-    pub async fn send_realtime_input(&mut self, json: serde_json::Value) -> Result<()> {
-        if self.state != ConnectionState::SetupComplete {
-            return Err(GeminiError::SetupNotComplete);
+    /// Send a typed outbound message (for channel-based architecture)
+    ///
+    /// While the connection is down the message is buffered and sent after the
+    /// next successful setup. A failed write is buffered as well and reported
+    /// so the caller can trigger a reconnect.
+    pub async fn send_outbound(&mut self, msg: WsOutbound) -> Result<()> {
+        if self.standby.is_some() && self.at_turn_boundary() {
+            if let Err(e) = self.promote_standby().await {
+                warn!("Could not switch to standby session: {}", e);
+            }
+        }
+        match msg {
+            WsOutbound::ActivityStart => self.in_turn = true,
+            WsOutbound::ActivityEnd => self.in_turn = false,
+            _ => {}
         }
 
-        // Parse the JSON value as RealtimeInput
-        let realtime_input: RealtimeInput = serde_json::from_value(json)?;
-        let message = ClientMessage::RealtimeInput { realtime_input };
-        let json_str = serde_json::to_string(&message)?;
+        let message = outbound_to_client_message(msg);
 
-        if let Some(ref writer) = self.ws_writer {
-            writer
-                .lock()
-                .await
-                .send(Message::Text(json_str.into()))
-                .await
-                .map_err(|e| GeminiError::WebSocket(e))?;
-        } else {
-            return Err(GeminiError::ConnectionClosed);
+        if self.state != ConnectionState::SetupComplete {
+            debug!("Not connected, buffering outbound message");
+            self.buffer_pending(message);
+            return Ok(());
+        }
+
+        if let Err(e) = self.write_message(&message).await {
+            self.state = ConnectionState::Disconnected;
+            self.buffer_pending(message);
+            return Err(e);
         }
 
         Ok(())
     }
 
-    /// Send raw client content JSON (for channel-based architecture)
-    pub async fn send_client_content(&mut self, json: serde_json::Value) -> Result<()> {
+    /// Send conversation content (for channel-based architecture)
+    pub async fn send_client_content(&mut self, client_content: ClientContent) -> Result<()> {
         if self.state != ConnectionState::SetupComplete {
             return Err(GeminiError::SetupNotComplete);
         }
 
-        let message = ClientMessage::ClientContent { client_content: json };
-        let json_str = serde_json::to_string(&message)?;
+        self.write_message(&ClientMessage::ClientContent { client_content })
+            .await
+    }
+
+    /// Serialize a client message and write it to the socket.
+    async fn write_message(&mut self, message: &ClientMessage) -> Result<()> {
+        let json_str = serde_json::to_string(message)?;
 
         if let Some(ref writer) = self.ws_writer {
+            let message = Message::Text(json_str.into());
+            if let Some(trace) = &self.trace {
+                trace.sent(&message);
+            }
             writer
                 .lock()
                 .await
-                .send(Message::Text(json_str.into()))
+                .send(message)
                 .await
                 .map_err(|e| GeminiError::WebSocket(e))?;
         } else {
             return Err(GeminiError::ConnectionClosed);
         }
 
+        // The server should answer a finished turn; start the watchdog
+        if let ClientMessage::RealtimeInput { realtime_input } = message {
+            if realtime_input.activity_end.is_some() {
+                let mut health = self.health.lock().unwrap();
+                health.awaiting_response_since.get_or_insert_with(Instant::now);
+                health.response_pending = true;
+            }
+        }
+
         Ok(())
     }
 }
 
-/// Process server content messages which can contain different types of data.
-async fn handle_server_content(
-    content: serde_json::Value,
+/// Sleep until `deadline`, or forever without one.
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Ping the server and report the connection as unresponsive once pings or
+/// a finished turn go unanswered for too long.
+async fn keepalive(
+    sink: WsSink,
+    health: Arc<std::sync::Mutex<Health>>,
+    config: GeminiClientConfig,
+    events_tx: mpsc::UnboundedSender<ConnectionEvent>,
+    trace: Option<ConnectionTrace>,
+) {
+    // Check often enough to notice a timeout soon after it expires
+    let Some(tick) = [config.keepalive_interval, config.response_timeout]
+        .into_iter()
+        .flatten()
+        .min()
+        .map(|shortest| shortest / 4)
+    else {
+        return;
+    };
+
+    let mut last_ping = Instant::now();
+    loop {
+        tokio::time::sleep(tick).await;
+        let now = Instant::now();
+
+        let status = *health.lock().unwrap();
+        if let Some(reason) = status.check(now, &config) {
+            warn!("Gemini connection unresponsive: {}", reason);
+            let _ = events_tx.send(ConnectionEvent::Unresponsive);
+            return;
+        }
+
+        if let Some(interval) = config.keepalive_interval {
+            if now.duration_since(last_ping) >= interval {
+                last_ping = now;
+                let ping = Message::Ping(Vec::new().into());
+                if let Some(trace) = &trace {
+                    trace.sent(&ping);
+                }
+                let sent = tokio::time::timeout(config.pong_timeout, async {
+                    sink.lock().await.send(ping).await
+                })
+                .await;
+                if !matches!(sent, Ok(Ok(()))) {
+                    warn!("Gemini connection unresponsive: could not send ping");
+                    let _ = events_tx.send(ConnectionEvent::Unresponsive);
+                    return;
+                }
+                debug!("Sent keepalive ping");
+            }
+        }
+    }
+}
+
+/// Convert an outbound event into the wire message it is sent as.
+///
+/// Each realtime input carries exactly one field; the API treats them as a union.
+fn outbound_to_client_message(msg: WsOutbound) -> ClientMessage {
+    let realtime_input = match msg {
+        WsOutbound::ActivityStart => RealtimeInput {
+            activity_start: Some(serde_json::json!({})),
+            ..Default::default()
+        },
+        WsOutbound::ActivityEnd => RealtimeInput {
+            activity_end: Some(serde_json::json!({})),
+            ..Default::default()
+        },
+        WsOutbound::Audio(pcm) => RealtimeInput {
+            audio: Some(RealtimeAudio {
+                data: general_purpose::STANDARD.encode(pcm),
+                mime_type: "audio/pcm;rate=16000".to_string(),
+            }),
+            ..Default::default()
+        },
+        WsOutbound::Video(jpeg) => RealtimeInput {
+            video: Some(RealtimeVideo {
+                data: general_purpose::STANDARD.encode(jpeg),
+                mime_type: "image/jpeg".to_string(),
+            }),
+            ..Default::default()
+        },
+        WsOutbound::Text(text) => RealtimeInput {
+            text: Some(text),
+            ..Default::default()
+        },
+        // Configuration (except the model) may be changed mid-session with a setup message
+        WsOutbound::ConfigUpdate(realtime_input_config) => {
+            return ClientMessage::Setup {
+                setup: BidiGenerateContentSetup {
+                    realtime_input_config: Some(realtime_input_config),
+                    ..Default::default()
+                },
+            }
+        }
+        WsOutbound::ToolResponse(tool_response) => {
+            return ClientMessage::ToolResponse { tool_response }
+        }
+        WsOutbound::ClientContent(client_content) => {
+            return ClientMessage::ClientContent { client_content }
+        }
+    };
+
+    ClientMessage::RealtimeInput { realtime_input }
+}
+
+/// Forward a parsed server message to subscribers, recording connection-level
+/// events and resumption handles along the way.
+async fn dispatch_server_message(
+    server_message: ServerMessage,
     response_tx: &mpsc::Sender<Result<ApiResponse>>,
+    events_tx: &mpsc::UnboundedSender<ConnectionEvent>,
+    session_token: &SessionHandle,
+    health: &std::sync::Mutex<Health>,
+    context: &mut ContextTracker,
 ) -> Result<()> {
-    // Check for input transcription (from audio we sent)
-    if let Some(input_transcription) = content.get("inputTranscription") {
-        // Safely extract text, providing a default if missing
-        let text = match input_transcription.get("text").and_then(|t| t.as_str()) {
-            Some(t) => t.to_string(),
-            None => {
-                tracing::warn!(
-                    "Received input transcription without text field: {:?}",
-                    input_transcription
-                );
-                String::new() // Empty string as fallback
+    let response = match server_message {
+        ServerMessage::SetupComplete { .. } => {
+            let _ = events_tx.send(ConnectionEvent::SetupComplete);
+            ApiResponse::SetupComplete
+        }
+        ServerMessage::ServerContent {
+            server_content,
+            usage_metadata,
+        } => {
+            if let Some(usage) = usage_metadata {
+                handle_usage_metadata(usage, response_tx, context).await?;
+            }
+            if server_content.turn_complete {
+                health.lock().unwrap().response_pending = false;
             }
-        };
 
-        // Safely extract isFinal flag
-        let is_final = input_transcription
-            .get("isFinal")
-            .and_then(|f| f.as_bool())
-            .unwrap_or(false);
-
-        // Only send if we have actual text content
-        if !text.is_empty() {
-            response_tx
-                .send(Ok(ApiResponse::InputTranscription(Transcript {
-                    text,
-                    is_final,
-                })))
+            // Process model content, transcriptions, etc.
+            return handle_server_content(*server_content, response_tx)
                 .await
-                .map_err(|_| {
-                    tracing::error!("Failed to send input transcription via channel");
-                    GeminiError::ChannelClosed
-                })?;
+                .map_err(|e| {
+                    error!("Failed to handle server content");
+                    e
+                });
+        }
+        ServerMessage::ToolCall { tool_call } => ApiResponse::ToolCall(tool_call),
+        ServerMessage::ToolCallCancellation {
+            tool_call_cancellation,
+        } => ApiResponse::ToolCallCancellation(tool_call_cancellation.ids),
+        ServerMessage::GoAway { go_away } => {
+            let time_left = go_away["timeLeft"].as_str().and_then(parse_duration);
+            warn!("Server sent GoAway, time left: {:?}", time_left);
+            let _ = events_tx.send(ConnectionEvent::GoAway(time_left));
+            ApiResponse::GoAway(time_left)
+        }
+        ServerMessage::SessionResumptionUpdate {
+            session_resumption_update,
+        } => {
+            let handle = session_resumption_update["newHandle"]
+                .as_str()
+                .unwrap_or("")
+                .to_string();
+            let resumable = session_resumption_update["resumable"]
+                .as_bool()
+                .unwrap_or(true);
+
+            // Only remember handles the server says we can actually resume from
+            if resumable && !handle.is_empty() {
+                debug!("Stored new session resumption handle");
+                *session_token.lock().unwrap() = Some(handle.clone());
+            }
+            ApiResponse::SessionResumptionUpdate(handle)
+        }
+        ServerMessage::UsageMetadata { usage_metadata } => {
+            return handle_usage_metadata(usage_metadata, response_tx, context).await;
         }
+    };
+
+    response_tx.send(Ok(response)).await.map_err(|_| {
+        error!("Failed to forward server message via channel");
+        GeminiError::ChannelClosed
+    })
+}
+
+/// Watches the prompt size reported in usage metadata to notice context compression.
+///
+/// The server does not announce compression; the prompt simply gets smaller.
+#[derive(Debug, Default)]
+struct ContextTracker {
+    last_prompt_tokens: Option<u64>,
+}
+
+impl ContextTracker {
+    /// Record a prompt size, returning `(before, after)` if the context shrank
+    fn observe(&mut self, prompt_tokens: u64) -> Option<(u64, u64)> {
+        let before = self.last_prompt_tokens.replace(prompt_tokens)?;
+        (prompt_tokens < before).then_some((before, prompt_tokens))
     }
+}
 
-    // Check for output transcription (text of model's speech)
-    if let Some(output_transcription) = content.get("outputTranscription") {
-        // Safely extract text, providing a default if missing
-        let text = match output_transcription.get("text").and_then(|t| t.as_str()) {
-            Some(t) => t.to_string(),
-            None => {
-                tracing::warn!(
-                    "Received output transcription without text field: {:?}",
-                    output_transcription
-                );
-                String::new() // Empty string as fallback
+/// Forward a usage update, reporting context compression if the prompt shrank.
+async fn handle_usage_metadata(
+    usage: UsageMetadata,
+    response_tx: &mpsc::Sender<Result<ApiResponse>>,
+    context: &mut ContextTracker,
+) -> Result<()> {
+    // Updates without a prompt count say nothing about the context size
+    let compressed = match usage.prompt_token_count {
+        0 => None,
+        prompt_tokens => context.observe(prompt_tokens),
+    };
+
+    response_tx.send(Ok(ApiResponse::Usage(usage))).await.map_err(|_| {
+        error!("Failed to send usage metadata via channel");
+        GeminiError::ChannelClosed
+    })?;
+
+    if let Some((before_tokens, after_tokens)) = compressed {
+        info!(
+            "🗜 Context window compressed: {} -> {} prompt tokens",
+            before_tokens, after_tokens
+        );
+        response_tx
+            .send(Ok(ApiResponse::ContextCompressed {
+                before_tokens,
+                after_tokens,
+            }))
+            .await
+            .map_err(|_| {
+                error!("Failed to send context compression via channel");
+                GeminiError::ChannelClosed
+            })?;
+    }
+    Ok(())
+}
+
+/// Process server content messages which can contain different types of data.
+async fn handle_server_content(
+    content: ServerContent,
+    response_tx: &mpsc::Sender<Result<ApiResponse>>,
+) -> Result<()> {
+    for response in server_content_responses(content) {
+        response_tx.send(Ok(response)).await.map_err(|_| {
+            tracing::error!("Failed to send server content via channel");
+            GeminiError::ChannelClosed
+        })?;
+    }
+    Ok(())
+}
+
+/// Split server content into the responses it carries, in delivery order.
+fn server_content_responses(content: ServerContent) -> Vec<ApiResponse> {
+    let mut responses = Vec::new();
+
+    // Transcriptions of the audio we sent and of the model's speech
+    if let Some(transcript) = content.input_transcription.filter(|t| !t.text.is_empty()) {
+        responses.push(ApiResponse::InputTranscription(transcript));
+    }
+    if let Some(transcript) = content.output_transcription.filter(|t| !t.text.is_empty()) {
+        responses.push(ApiResponse::OutputTranscription(transcript));
+    }
+
+    if content.interrupted {
+        tracing::info!("Generation interrupted by user activity");
+        responses.push(ApiResponse::Interrupted);
+    }
+
+    if content.generation_complete {
+        tracing::info!("Generation complete received from Gemini");
+        responses.push(ApiResponse::GenerationComplete);
+    }
+
+    // The model turn (the actual response)
+    let is_complete = content.generation_complete;
+    for part in content.model_turn.map(|turn| turn.parts).unwrap_or_default() {
+        if !part.extra.is_empty() {
+            warn!(
+                "Model turn part has unsupported fields: {:?}",
+                part.extra.keys().collect::<Vec<_>>()
+            );
+        }
+
+        if let Some(text) = part.text.filter(|t| !t.is_empty()) {
+            if part.thought == Some(true) {
+                responses.push(ApiResponse::Thought(text));
+            } else {
+                responses.push(ApiResponse::TextResponse { text, is_complete });
             }
-        };
+        }
 
-        // Safely extract isFinal flag
-        let is_final = output_transcription
-            .get("isFinal")
-            .and_then(|f| f.as_bool())
-            .unwrap_or(false);
-
-        // Only send if we have actual text content
-        if !text.is_empty() {
-            response_tx
-                .send(Ok(ApiResponse::OutputTranscription(Transcript {
-                    text,
-                    is_final,
-                })))
-                .await
-                .map_err(|_| {
-                    tracing::error!("Failed to send output transcription via channel");
-                    GeminiError::ChannelClosed
-                })?;
+        if let Some(inline_data) = part.inline_data {
+            match general_purpose::STANDARD.decode(&inline_data.data) {
+                // Only send if we have actual data
+                Ok(data) if !data.is_empty() => {
+                    responses.push(ApiResponse::AudioResponse { data, is_complete })
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    // Continue processing other parts even if one fails
+                    tracing::error!("Failed to decode base64 {} data: {:?}", inline_data.mime_type, e);
+                }
+            }
+        }
+
+        if let Some(code) = part.executable_code {
+            responses.push(ApiResponse::ExecutableCode(code));
         }
+        if let Some(result) = part.code_execution_result {
+            responses.push(ApiResponse::CodeExecutionResult(result));
+        }
+    }
+
+    if let Some(grounding) = content.grounding_metadata {
+        responses.push(ApiResponse::Grounding(grounding));
+    }
+
+    if !content.extra.is_empty() {
+        debug!(
+            "Server content has unhandled fields: {:?}",
+            content.extra.keys().collect::<Vec<_>>()
+        );
+    }
+
+    // turnComplete comes after any content in the same message
+    if content.turn_complete {
+        tracing::info!("Turn complete received from Gemini");
+        responses.push(ApiResponse::TurnComplete);
+    }
+
+    responses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gemini::{FunctionResponse, ServerErrorKind};
+    use crate::gemini::ToolResponse;
+    use crate::mock_live::{MockLiveServer, Reply, Script, Trigger};
+    use crate::simple_turn_fsm::{Event, SimpleTurnFsm};
+    use serde_json::json;
+
+    fn client_for(server: &MockLiveServer) -> GeminiClient {
+        GeminiClient::new(GeminiClientConfig {
+            url: server.url(),
+            reconnect_delay: Duration::from_millis(10),
+            ..Default::default()
+        })
     }
 
-    // Check for generationComplete flag
-    if let Some(generation_complete) = content.get("generationComplete").and_then(|g| g.as_bool()) {
-        if generation_complete {
-            tracing::info!("Generation complete received from Gemini");
-            response_tx
-                .send(Ok(ApiResponse::GenerationComplete))
+    /// Next response, skipping setup acknowledgements and resumption updates
+    async fn next(rx: &mut mpsc::Receiver<Result<ApiResponse>>) -> ApiResponse {
+        loop {
+            let response = tokio::time::timeout(Duration::from_secs(5), rx.recv())
                 .await
-                .map_err(|_| {
-                    tracing::error!("Failed to send GenerationComplete via channel");
-                    GeminiError::ChannelClosed
-                })?;
+                .expect("timed out waiting for a response")
+                .expect("response channel closed")
+                .expect("client reported an error");
+            if !matches!(
+                response,
+                ApiResponse::SetupComplete | ApiResponse::SessionResumptionUpdate(_)
+            ) {
+                return response;
+            }
         }
     }
 
-    // Check for model turn (the actual response)
-    if let Some(model_turn) = content.get("modelTurn") {
-        // Get parts array, log warning if missing
-        let parts = match model_turn.get("parts").and_then(|p| p.as_array()) {
-            Some(parts) => parts,
-            None => {
-                tracing::warn!("Received model turn without parts array: {:?}", model_turn);
-                return Ok(()); // Skip processing if no parts
+    /// Wait until the server has received `count` messages matching the trigger
+    async fn wait_for(server: &MockLiveServer, trigger: Trigger, count: usize) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while server.received_matching(trigger).len() < count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
             }
+        })
+        .await
+        .expect("timed out waiting for the server to receive a message");
+    }
+
+    #[tokio::test]
+    async fn test_fsm_turn_is_answered() {
+        let server =
+            MockLiveServer::start(Script::new().on(Trigger::ActivityEnd, Reply::Text("hi".into())))
+                .await;
+        let mut client = client_for(&server);
+        client.connect_and_setup().await.unwrap();
+        let mut rx = client.subscribe();
+
+        // A two-frame video turn produced by the FSM
+        let (media_tx, _media_rx) = tokio::sync::broadcast::channel(8);
+        let mut fsm = SimpleTurnFsm::new(media_tx);
+        fsm.on_event(Event::Frame { jpeg: vec![1], hash: 1 });
+        fsm.on_event(Event::Frame { jpeg: vec![2], hash: 2 });
+        for msg in fsm.drain_messages() {
+            client.send_outbound(msg).await.unwrap();
+        }
+
+        assert!(matches!(next(&mut rx).await, ApiResponse::TextResponse { text, .. } if text == "hi"));
+        assert!(matches!(next(&mut rx).await, ApiResponse::GenerationComplete));
+        assert!(matches!(next(&mut rx).await, ApiResponse::TurnComplete));
+
+        let received = server.received();
+        assert_eq!(received[0]["setup"]["model"], "models/gemini-2.0-flash-live-001");
+        assert!(received[1]["realtimeInput"].get("activityStart").is_some());
+        assert_eq!(received[2]["realtimeInput"]["video"]["mimeType"], "image/jpeg");
+        assert!(received[4]["realtimeInput"].get("activityEnd").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_vertex_setup_uses_publisher_model_and_bearer_token() {
+        let server = MockLiveServer::start(Script::new()).await;
+        let vertex = Backend::VertexAi {
+            project: "my-project".to_string(),
+            location: "us-central1".to_string(),
         };
+        let auth = Arc::new(crate::auth::BearerTokenCommand::new(
+            "echo ya29.token",
+            Duration::from_secs(3600),
+        ));
+        let mut client = GeminiClient::with_auth(
+            auth,
+            Some(GeminiClientConfig {
+                url: server.url(),
+                backend: vertex.clone(),
+                ..Default::default()
+            }),
+        );
+        client.connect_and_setup().await.unwrap();
 
-        // Safely extract completion flag
-        let is_complete = content
-            .get("generationComplete")
-            .and_then(|g| g.as_bool())
-            .unwrap_or(false);
-
-        // Process each part in the response
-        for part in parts {
-            // Check for text response
-            if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
-                if !text.is_empty() {
-                    response_tx
-                        .send(Ok(ApiResponse::TextResponse {
-                            text: text.to_string(),
-                            is_complete,
-                        }))
-                        .await
-                        .map_err(|_| {
-                            tracing::error!("Failed to send text response via channel");
-                            GeminiError::ChannelClosed
-                        })?;
-                }
-            }
-            // Check for audio response (inline data)
-            else if let Some(inline_data) = part.get("inlineData") {
-                // Try to extract and decode the base64 data
-                match inline_data.get("data").and_then(|d| d.as_str()) {
-                    Some(data_str) => {
-                        match general_purpose::STANDARD.decode(data_str) {
-                            Ok(data) => {
-                                // Only send if we have actual data
-                                if !data.is_empty() {
-                                    response_tx
-                                        .send(Ok(ApiResponse::AudioResponse { data, is_complete }))
-                                        .await
-                                        .map_err(|_| {
-                                            tracing::error!(
-                                                "Failed to send audio response via channel"
-                                            );
-                                            GeminiError::ChannelClosed
-                                        })?;
-                                }
-                            }
-                            Err(e) => {
-                                tracing::error!("Failed to decode base64 audio data: {:?}", e);
-                                // Continue processing other parts even if one fails
-                            }
-                        }
-                    }
-                    None => {
-                        tracing::warn!(
-                            "Received inline data without data field: {:?}",
-                            inline_data
-                        );
-                    }
-                }
+        assert_eq!(server.handshake_headers()[0]["authorization"], "Bearer ya29.token");
+        assert_eq!(
+            server.received()[0]["setup"]["model"],
+            "projects/my-project/locations/us-central1/publishers/google/models/gemini-2.0-flash-live-001"
+        );
+
+        // API keys are refused before connecting
+        let mut client = GeminiClient::from_api_key(
+            "key",
+            Some(GeminiClientConfig {
+                url: server.url(),
+                backend: vertex,
+                ..Default::default()
+            }),
+        );
+        assert!(matches!(client.connect().await, Err(GeminiError::Auth(_))));
+        assert_eq!(server.connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_go_away_reconnects_with_resumption_handle() {
+        let server = MockLiveServer::start(
+            Script::new()
+                .on(Trigger::Setup, Reply::ResumptionHandle("handle-1".into()))
+                .once(Trigger::ActivityEnd, Reply::GoAway(Duration::from_secs(5))),
+        )
+        .await;
+        let mut client = client_for(&server);
+        client.connect_and_setup().await.unwrap();
+
+        client.send_outbound(WsOutbound::ActivityEnd).await.unwrap();
+        assert_eq!(
+            client.connection_lost().await,
+            ConnectionEvent::GoAway(Some(Duration::from_secs(5)))
+        );
+        client.reconnect().await.unwrap();
+
+        assert_eq!(server.connections(), 2);
+        let setups = server.received_matching(Trigger::Setup);
+        assert_eq!(setups.len(), 2);
+        assert!(setups[0]["setup"]["sessionResumption"].get("handle").is_none());
+        assert_eq!(setups[1]["setup"]["sessionResumption"]["handle"], "handle-1");
+    }
+
+    #[tokio::test]
+    async fn test_new_session_is_seeded_with_context_once() {
+        let server = MockLiveServer::start(
+            Script::new().on(Trigger::Setup, Reply::ResumptionHandle("handle-1".into())),
+        )
+        .await;
+        let mut client = GeminiClient::new(GeminiClientConfig {
+            url: server.url(),
+            initial_context: vec![Content {
+                role: Some("user".to_string()),
+                parts: vec![Part {
+                    text: Some("We were fixing the parser".to_string()),
+                    ..Default::default()
+                }],
+            }],
+            ..Default::default()
+        });
+        client.connect_and_setup().await.unwrap();
+        client.send_outbound(WsOutbound::ActivityEnd).await.unwrap();
+        wait_for(&server, Trigger::ActivityEnd, 1).await;
+
+        // The context is seeded right after setup, before any input
+        let received = server.received();
+        assert_eq!(received[1]["clientContent"]["turnComplete"], false);
+        assert_eq!(
+            received[1]["clientContent"]["turns"][0]["parts"][0]["text"],
+            "We were fixing the parser"
+        );
+
+        // Not again when resuming, the server still has it
+        client.reconnect().await.unwrap();
+        client.send_outbound(WsOutbound::ActivityEnd).await.unwrap();
+        wait_for(&server, Trigger::ActivityEnd, 2).await;
+        assert_eq!(server.received_matching(Trigger::Setup).len(), 2);
+        assert_eq!(server.received_matching(Trigger::ClientContent).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_go_away_hands_over_to_standby_at_turn_boundary() {
+        let server = MockLiveServer::start(
+            Script::new()
+                .on(Trigger::Setup, Reply::ResumptionHandle("handle-1".into()))
+                .once(Trigger::ActivityEnd, Reply::GoAway(Duration::from_secs(5)))
+                .on(Trigger::ActivityEnd, Reply::Text("hi".into())),
+        )
+        .await;
+        let mut client = client_for(&server);
+        client.connect_and_setup().await.unwrap();
+        let mut rx = client.subscribe();
+
+        client.send_outbound(WsOutbound::ActivityStart).await.unwrap();
+        client.send_outbound(WsOutbound::ActivityEnd).await.unwrap();
+        assert_eq!(
+            client.connection_lost().await,
+            ConnectionEvent::GoAway(Some(Duration::from_secs(5)))
+        );
+        client.prepare_standby().await.unwrap();
+
+        // The response in flight on the old session still arrives
+        assert!(matches!(next(&mut rx).await, ApiResponse::GoAway(_)));
+        assert!(matches!(next(&mut rx).await, ApiResponse::TextResponse { text, .. } if text == "hi"));
+        assert!(matches!(next(&mut rx).await, ApiResponse::GenerationComplete));
+        assert!(matches!(next(&mut rx).await, ApiResponse::TurnComplete));
+
+        // The next turn goes to the standby, which resumed the session
+        client.send_outbound(WsOutbound::ActivityStart).await.unwrap();
+        client.send_outbound(WsOutbound::ActivityEnd).await.unwrap();
+        assert!(matches!(next(&mut rx).await, ApiResponse::TextResponse { text, .. } if text == "hi"));
+
+        assert_eq!(server.connections(), 2);
+        let standby = server.received_on(1);
+        assert_eq!(standby[0]["setup"]["sessionResumption"]["handle"], "handle-1");
+        assert!(standby[1]["realtimeInput"].get("activityStart").is_some());
+        assert_eq!(server.received_matching(Trigger::ActivityEnd).len(), 2);
+        assert_eq!(server.received_on(0).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_resumes_with_new_setup() {
+        let server = MockLiveServer::start(
+            Script::new().on(Trigger::Setup, Reply::ResumptionHandle("handle-1".into())),
+        )
+        .await;
+        let mut client = client_for(&server);
+        client.connect_and_setup().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client.session_token.lock().unwrap().is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
             }
-        }
+        })
+        .await
+        .unwrap();
+
+        let mut config = client.config().clone();
+        config.system_instruction = Some("Help with the meeting".to_string());
+        config.temperature = Some(0.2);
+        client.reconfigure(config).await.unwrap();
+
+        assert_eq!(server.connections(), 2);
+        let setup = &server.received_on(1)[0]["setup"];
+        assert_eq!(setup["systemInstruction"]["parts"][0]["text"], "Help with the meeting");
+        assert_eq!(setup["generationConfig"]["temperature"].as_f64().unwrap() as f32, 0.2);
+        assert_eq!(setup["sessionResumption"]["handle"], "handle-1");
     }
 
-    Ok(())
+    #[tokio::test]
+    async fn test_fatal_close_stops_reconnecting() {
+        let server = MockLiveServer::start(Script::new().on(
+            Trigger::Setup,
+            Reply::Close(1008, "models/gemini-0.1 is not found for API version v1beta".into()),
+        ))
+        .await;
+        let mut client = client_for(&server);
+        let error = client.connect_and_setup().await.unwrap_err();
+        assert!(matches!(&error, GeminiError::Server(e) if e.kind == ServerErrorKind::ModelNotFound));
+
+        // Default config allows several attempts, a fatal error ends them at once
+        assert!(client.reconnect().await.is_err());
+        assert_eq!(server.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_reconnects_fresh() {
+        let server = MockLiveServer::start(
+            Script::new()
+                .once(Trigger::Setup, Reply::ResumptionHandle("handle-1".into()))
+                .once(Trigger::ActivityStart, Reply::Close(1008, "Session expired".into())),
+        )
+        .await;
+        let mut client = client_for(&server);
+        client.connect_and_setup().await.unwrap();
+        client.send_outbound(WsOutbound::ActivityStart).await.unwrap();
+
+        let event = client.connection_lost().await;
+        assert!(matches!(
+            event,
+            ConnectionEvent::Failed(ServerError { kind: ServerErrorKind::SessionExpired, .. })
+        ));
+        client.reconnect().await.unwrap();
+        assert!(server.received_on(1)[0]["setup"]["sessionResumption"].get("handle").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_wire_trace_records_both_directions() {
+        let server = MockLiveServer::start(Script::new()).await;
+        let path = std::env::temp_dir().join(format!("rholive-wire-{}.jsonl", std::process::id()));
+        let mut client = client_for(&server);
+        let tracer = WireTracer::create(&path, None).unwrap();
+        client.set_tracer(tracer.clone());
+        client.connect_and_setup().await.unwrap();
+        client.send_outbound(WsOutbound::Audio(vec![0; 640])).await.unwrap();
+
+        tracer.flush();
+        let trace = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<serde_json::Value> =
+            trace.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines[1]["kind"], "connect");
+        assert_eq!(lines[2]["dir"], "send");
+        assert!(lines[2]["msg"].get("setup").is_some());
+        assert_eq!(lines[3]["dir"], "recv");
+        assert!(lines[3]["msg"].get("setupComplete").is_some());
+        assert_eq!(lines[4]["msg"]["realtimeInput"]["audio"]["data"]["bytes"], 640);
+    }
+
+    #[tokio::test]
+    async fn test_connection_lifetime_triggers_rotation() {
+        let server = MockLiveServer::start(Script::new()).await;
+        let mut client = GeminiClient::new(GeminiClientConfig {
+            url: server.url(),
+            connection_lifetime: Some(Duration::from_millis(100)),
+            ..Default::default()
+        });
+        client.connect_and_setup().await.unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), client.connection_lost())
+            .await
+            .unwrap();
+        assert_eq!(event, ConnectionEvent::GoAway(None));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_connection_flushes_buffered_input() {
+        let server =
+            MockLiveServer::start(Script::new().once(Trigger::ActivityStart, Reply::Drop)).await;
+        let mut client = client_for(&server);
+        client.connect_and_setup().await.unwrap();
+
+        client.send_outbound(WsOutbound::ActivityStart).await.unwrap();
+        assert_eq!(client.connection_lost().await, ConnectionEvent::Closed);
+
+        // Buffered while disconnected and sent once the session is back
+        client.send_outbound(WsOutbound::Text("still here".into())).await.unwrap();
+        client.reconnect().await.unwrap();
+        client.send_outbound(WsOutbound::ActivityEnd).await.unwrap();
+        wait_for(&server, Trigger::ActivityEnd, 1).await;
+        let texts: Vec<_> = server
+            .received()
+            .into_iter()
+            .filter(|msg| msg["realtimeInput"]["text"] == "still here")
+            .collect();
+        assert_eq!(texts.len(), 1);
+    }
+
+    #[test]
+    fn test_health_check() {
+        let config = GeminiClientConfig {
+            keepalive_interval: Some(Duration::from_secs(15)),
+            pong_timeout: Duration::from_secs(10),
+            response_timeout: Some(Duration::from_secs(30)),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let mut health = Health::new(start);
+        assert_eq!(health.check(start + Duration::from_secs(20), &config), None);
+        assert!(health.check(start + Duration::from_secs(26), &config).is_some());
+
+        // Pongs keep the socket alive, but only messages answer a turn
+        health.awaiting_response_since = Some(start);
+        health.on_frame(start + Duration::from_secs(20), false);
+        assert_eq!(health.check(start + Duration::from_secs(25), &config), None);
+        assert!(health.check(start + Duration::from_secs(31), &config).is_some());
+        health.on_frame(start + Duration::from_secs(30), true);
+        assert_eq!(health.check(start + Duration::from_secs(31), &config), None);
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_pings_mark_connection_unresponsive() {
+        let server =
+            MockLiveServer::start(Script::new().once(Trigger::ActivityStart, Reply::Stall)).await;
+        let mut client = GeminiClient::new(GeminiClientConfig {
+            url: server.url(),
+            keepalive_interval: Some(Duration::from_millis(50)),
+            pong_timeout: Duration::from_millis(100),
+            response_timeout: None,
+            ..Default::default()
+        });
+        client.connect_and_setup().await.unwrap();
+
+        client.send_outbound(WsOutbound::ActivityStart).await.unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), client.connection_lost())
+            .await
+            .expect("stalled connection was not detected");
+        assert_eq!(event, ConnectionEvent::Unresponsive);
+    }
+
+    #[tokio::test]
+    async fn test_silence_after_activity_end_marks_connection_unresponsive() {
+        let server = MockLiveServer::start(Script::new()).await;
+        let mut client = GeminiClient::new(GeminiClientConfig {
+            url: server.url(),
+            keepalive_interval: None,
+            response_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        });
+        client.connect_and_setup().await.unwrap();
+
+        client.send_outbound(WsOutbound::ActivityEnd).await.unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), client.connection_lost())
+            .await
+            .expect("silent server was not detected");
+        assert_eq!(event, ConnectionEvent::Unresponsive);
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_round_trip() {
+        let server = MockLiveServer::start(
+            Script::new()
+                .on(
+                    Trigger::ActivityEnd,
+                    Reply::ToolCall {
+                        id: "call-1".into(),
+                        name: "get_current_time".into(),
+                        args: json!({}),
+                    },
+                )
+                .on(Trigger::ToolResponse, Reply::Text("it is noon".into())),
+        )
+        .await;
+        let mut client = client_for(&server);
+        client.connect_and_setup().await.unwrap();
+        let mut rx = client.subscribe();
+
+        client.send_outbound(WsOutbound::ActivityEnd).await.unwrap();
+        let ApiResponse::ToolCall(tool_call) = next(&mut rx).await else {
+            panic!("expected a tool call");
+        };
+        assert_eq!(tool_call.function_calls[0].id, "call-1");
+
+        client
+            .send_outbound(WsOutbound::ToolResponse(ToolResponse {
+                function_responses: vec![FunctionResponse {
+                    id: "call-1".into(),
+                    name: "get_current_time".into(),
+                    response: json!({ "output": "12:00" }),
+                    scheduling: None,
+                }],
+            }))
+            .await
+            .unwrap();
+        assert!(matches!(next(&mut rx).await, ApiResponse::TextResponse { text, .. } if text == "it is noon"));
+
+        let responses = server.received_matching(Trigger::ToolResponse);
+        assert_eq!(
+            responses[0]["toolResponse"]["functionResponses"][0]["response"]["output"],
+            "12:00"
+        );
+    }
+
+    #[test]
+    fn test_server_content_responses_in_order() {
+        let content: ServerContent = serde_json::from_value(json!({
+            "modelTurn": { "parts": [
+                { "text": "thinking", "thought": true },
+                { "executableCode": { "language": "PYTHON", "code": "print(1)" } },
+                { "codeExecutionResult": { "outcome": "OUTCOME_OK", "output": "1\n" } },
+                { "text": "one" },
+                { "inlineData": { "mimeType": "audio/pcm", "data": "AAE=" } }
+            ] },
+            "groundingMetadata": { "webSearchQueries": ["one"] },
+            "turnComplete": true,
+            "turnCompleteReason": "NEW_FIELD"
+        }))
+        .unwrap();
+        assert_eq!(content.extra["turnCompleteReason"], "NEW_FIELD");
+
+        let responses = server_content_responses(content);
+        assert!(matches!(&responses[0], ApiResponse::Thought(t) if t == "thinking"));
+        assert!(matches!(&responses[1], ApiResponse::ExecutableCode(c) if c.code == "print(1)"));
+        assert!(matches!(&responses[2], ApiResponse::CodeExecutionResult(r) if r.outcome == "OUTCOME_OK"));
+        assert!(matches!(&responses[3], ApiResponse::TextResponse { text, .. } if text == "one"));
+        assert!(matches!(&responses[4], ApiResponse::AudioResponse { data, .. } if data == &[0, 1]));
+        assert!(matches!(&responses[5], ApiResponse::Grounding(g) if g.web_search_queries == ["one"]));
+        assert!(matches!(responses[6], ApiResponse::TurnComplete));
+        assert_eq!(responses.len(), 7);
+    }
+
+    #[test]
+    fn test_context_tracker_reports_shrinking_prompt() {
+        let mut context = ContextTracker::default();
+        assert_eq!(context.observe(1000), None);
+        assert_eq!(context.observe(25_000), None);
+        assert_eq!(context.observe(12_000), Some((25_000, 12_000)));
+        assert_eq!(context.observe(12_500), None);
+    }
 }
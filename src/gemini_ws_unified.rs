@@ -1,54 +1,130 @@
-//! Unified Gemini WebSocket handler
+//! Unified live model WebSocket handler
 
+use crate::escalation::Escalator;
 use crate::media_event::{WsOutbound, WsInbound};
-use crate::gemini_client::GeminiClient;
-use crate::gemini::{ApiResponse, GeminiClientConfig};
+use crate::gemini_client::ConnectionEvent;
+use crate::gemini::{ApiResponse, Retry, MAX_RECONNECT_DELAY};
+use crate::live_backend::{LiveModelBackend, SessionSetup};
+use crate::send_queue::{SendQueueConfig, SendQueueStats, SharedSendQueue};
+use crate::tools::ToolRegistry;
+use crate::usage::{BudgetAction, TokenBudget, TokenCounts, UsageTracker};
 use anyhow::Result;
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
-use tracing::{debug, error, info};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::{debug, error, info, warn};
+
+/// How often queue depth and drops are reported while they change
+const QUEUE_REPORT_INTERVAL: Duration = Duration::from_secs(1);
 
 pub async fn run(
-    api_key: &str,
-    mut rx_out: UnboundedReceiver<WsOutbound>,
+    mut backend: Box<dyn LiveModelBackend>,
+    rx_out: UnboundedReceiver<WsOutbound>,
     tx_in: UnboundedSender<WsInbound>,
+    tools: ToolRegistry,
+    token_budget: Option<TokenBudget>,
+    escalation: Option<Arc<Escalator>>,
+    mut setup_rx: UnboundedReceiver<SessionSetup>,
 ) -> Result<()> {
-    let mut config = GeminiClientConfig::default();
-    config.system_instruction = Some(
-        "you are, rholive, a silent helper meant to assist the user in whatever task they choose. if you see a leetcode problem on the screen, solve it without waiting for them to say anything. if someone they are on call with asks you a question, answer it. you are effectively their second mind, they should not have to do any thinking, they should not have to ask you for anything. you are their brain, they should not have to think, respond to whatever is on screen or whatever someone says like the user would.
-          you have access to the users screen, microphone and system audio.
-            when there is no change or nothing to work, do, or comment on, respond only with '<nothing>' (without quotes). if you don't understand what is going on, respond only with '<nothing>'. please be quiet until the user asks you something or u know what to do (i.e. respond with '<nothing>').
-            keep in mind, the user can only really see a few lines, so when you respond start with first thing wait and continually do more.
-            "
-        .to_string()
-    );
+    backend.connect().await?;
     
-    let mut client = GeminiClient::from_api_key(api_key, Some(config));
+    let mut response_rx = backend.subscribe();
     
-    client.connect().await?;
-    client.setup().await?;
+    // Tool results are sent through the same writer as media
+    let (tool_tx, tool_rx) = mpsc::unbounded_channel::<WsOutbound>();
     
-    let mut response_rx = client.subscribe();
+    // Set once the token budget is spent with `BudgetAction::PauseVideo`
+    let video_paused = Arc::new(AtomicBool::new(false));
+    let mut usage = UsageTracker::new(token_budget);
     
-    // Handle outgoing messages
+    // Keep accepting messages while the socket is slow, shedding stale media
+    let queue = Arc::new(SharedSendQueue::new(SendQueueConfig::default()));
+    tokio::spawn(fill_queue(rx_out, tool_rx, queue.clone(), video_paused.clone()));
+    
+    // Handle outgoing messages, reconnecting whenever the socket drops
+    let tx_status = tx_in.clone();
     tokio::spawn(async move {
-        while let Some(msg) = rx_out.recv().await {
-            match msg {
-                WsOutbound::Json(json) => {
-                    // Log message type for debugging
-                    if json.get("activityStart").is_some() {
-                        info!(">>> Sending activityStart");
-                    } else if json.get("activityEnd").is_some() {
-                        info!(">>> Sending activityEnd");
-                    } else if json.get("audio").is_some() {
-                        debug!(">>> Sending audio chunk");
-                    } else if json.get("video").is_some() {
-                        debug!(">>> Sending video frame");
-                    }
-                    
-                    if let Err(e) = client.send_realtime_input(json).await {
-                        error!("Error sending to Gemini: {}", e);
+        let mut reported = SendQueueStats::default();
+        let mut last_report = Instant::now();
+        loop {
+            let msg = tokio::select! {
+                msg = queue.pop() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+                event = backend.connection_lost() => {
+                    if let ConnectionEvent::GoAway(time_left) = event {
+                        // The old session keeps serving until the backend has a replacement
+                        info!("{} session ending (time left: {:?})", backend.name(), time_left);
+                        if let Err(e) = backend.session_ending().await {
+                            warn!("Could not prepare replacement session: {}", e);
+                        }
+                        continue;
                     }
+                    if let ConnectionEvent::Failed(e) = &event {
+                        match e.kind.retry() {
+                            Retry::Fatal => {
+                                error!("{} session failed: {}", backend.name(), e);
+                                let _ = tx_status.send(WsInbound::Fatal(e.to_string()));
+                                break;
+                            }
+                            Retry::Backoff => {
+                                warn!(
+                                    "{} {}, waiting {:?} before reconnecting",
+                                    backend.name(),
+                                    e,
+                                    MAX_RECONNECT_DELAY
+                                );
+                                tokio::time::sleep(MAX_RECONNECT_DELAY).await;
+                            }
+                            Retry::Resume | Retry::Fresh => {}
+                        }
+                    }
+                    warn!("{} connection lost ({:?}), reconnecting", backend.name(), event);
+                    if !reconnect(backend.as_mut(), &tx_status).await {
+                        break;
+                    }
+                    continue;
+                }
+                Some(setup) = setup_rx.recv() => {
+                    // Profile switch; a failed switch leaves the session to the reconnect path
+                    info!("Applying new session setup to {}", backend.name());
+                    if let Err(e) = backend.apply_setup(setup).await {
+                        error!("Could not apply session setup: {}", e);
+                        let _ = tx_status.send(WsInbound::Error(format!("Profile switch failed: {}", e)));
+                    }
+                    continue;
                 }
+            };
+
+            // Log message type for debugging
+            match &msg {
+                WsOutbound::ActivityStart => info!(">>> Sending activityStart"),
+                WsOutbound::ActivityEnd => info!(">>> Sending activityEnd"),
+                WsOutbound::Audio(_) => debug!(">>> Sending audio chunk"),
+                WsOutbound::Video(_) => debug!(">>> Sending video frame"),
+                WsOutbound::Text(_) => debug!(">>> Sending text"),
+                WsOutbound::ConfigUpdate(config) => info!(">>> Sending config update: {:?}", config),
+                WsOutbound::ToolResponse(_) => info!(">>> Sending tool response"),
+                WsOutbound::ClientContent(_) => info!(">>> Sending client content"),
+            }
+
+            if let Err(e) = backend.send(msg).await {
+                error!("Error sending to {}: {}", backend.name(), e);
+                if !reconnect(backend.as_mut(), &tx_status).await {
+                    break;
+                }
+            }
+
+            let stats = queue.stats();
+            if stats != reported && last_report.elapsed() >= QUEUE_REPORT_INTERVAL {
+                if stats.lost() > reported.lost() {
+                    warn!("Send queue shedding media: {:?}", stats);
+                }
+                let _ = tx_status.send(WsInbound::SendQueue(stats));
+                reported = stats;
+                last_report = Instant::now();
             }
         }
     });
@@ -57,6 +133,9 @@ pub async fn run(
     while let Some(response) = response_rx.recv().await {
         match response {
             Ok(api_response) => {
+                if let Some(escalator) = &escalation {
+                    escalator.observe(&api_response);
+                }
                 let ws_in = match api_response {
                     ApiResponse::TextResponse { text, is_complete } => {
                         if is_complete {
@@ -65,22 +144,107 @@ pub async fn run(
                         }
                         Some(WsInbound::Text { content: text, is_final: is_complete })
                     }
+                    ApiResponse::AudioResponse { data, .. } => {
+                        debug!("<<< Audio response: {} bytes", data.len());
+                        Some(WsInbound::Audio(data))
+                    }
+                    ApiResponse::InputTranscription(transcript) => {
+                        debug!("<<< Input transcription: {}", transcript.text);
+                        Some(WsInbound::InputTranscription {
+                            text: transcript.text,
+                            is_final: transcript.is_final,
+                        })
+                    }
+                    ApiResponse::OutputTranscription(transcript) => {
+                        debug!("<<< Output transcription: {}", transcript.text);
+                        Some(WsInbound::OutputTranscription {
+                            text: transcript.text,
+                            is_final: transcript.is_final,
+                        })
+                    }
+                    ApiResponse::ExecutableCode(code) => {
+                        info!("<<< Executable code ({})", code.language);
+                        Some(WsInbound::CodeExecution {
+                            language: code.language,
+                            code: code.code,
+                        })
+                    }
+                    ApiResponse::CodeExecutionResult(result) => {
+                        info!("<<< Code execution result: {}", result.outcome);
+                        Some(WsInbound::CodeExecutionResult {
+                            outcome: result.outcome,
+                            output: result.output.unwrap_or_default(),
+                        })
+                    }
+                    ApiResponse::Grounding(grounding) => {
+                        info!("<<< Grounding: {:?}", grounding.web_search_queries);
+                        Some(WsInbound::Sources {
+                            queries: grounding.web_search_queries,
+                            sources: grounding
+                                .grounding_chunks
+                                .into_iter()
+                                .filter_map(|chunk| chunk.web)
+                                .collect(),
+                        })
+                    }
+                    ApiResponse::Thought(thought) => {
+                        debug!("<<< Thought: {}", thought);
+                        None
+                    }
                     ApiResponse::GenerationComplete => {
                         info!("<<< Generation complete");
                         Some(WsInbound::GenerationComplete)
                     }
-                    ApiResponse::ToolCall(tool_call) => {
-                        Some(WsInbound::ToolCall { 
-                            name: tool_call.get("name")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("")
-                                .to_string(),
-                            args: tool_call
+                    ApiResponse::Interrupted => {
+                        info!("<<< Interrupted");
+                        Some(WsInbound::Interrupted)
+                    }
+                    ApiResponse::TurnComplete => {
+                        info!("<<< Turn complete");
+                        Some(WsInbound::TurnComplete)
+                    }
+                    ApiResponse::Usage(metadata) => {
+                        if let Some(action) = usage.record(&metadata) {
+                            if action == BudgetAction::PauseVideo {
+                                video_paused.store(true, Ordering::Relaxed);
+                            }
+                            let _ = tx_in.send(WsInbound::TokenBudgetExceeded(action));
+                        }
+                        Some(WsInbound::Usage {
+                            turn: TokenCounts::from(&metadata),
+                            session: usage.session().clone(),
                         })
                     }
+                    ApiResponse::ContextCompressed { before_tokens, after_tokens } => {
+                        info!("<<< Context compressed: {} -> {} tokens", before_tokens, after_tokens);
+                        Some(WsInbound::ContextCompressed { before_tokens, after_tokens })
+                    }
+                    ApiResponse::ToolCall(tool_call) => {
+                        // Surface the calls, then answer them in the background
+                        for call in &tool_call.function_calls {
+                            info!("<<< Tool call: {} (id: {})", call.name, call.id);
+                            let _ = tx_in.send(WsInbound::ToolCall {
+                                id: call.id.clone(),
+                                name: call.name.clone(),
+                                args: call.args.clone(),
+                            });
+                        }
+                        tools.dispatch(tool_call, tool_tx.clone());
+                        None
+                    }
+                    ApiResponse::ToolCallCancellation(ids) => {
+                        info!("<<< Tool call cancellation: {:?}", ids);
+                        tools.cancel(&ids);
+                        None
+                    }
                     ApiResponse::ConnectionClosed => {
-                        error!("Gemini connection closed");
-                        break;
+                        // The writer task reconnects; we keep listening on the same channel
+                        warn!("Live model connection closed");
+                        None
+                    }
+                    ApiResponse::GoAway(time_left) => {
+                        warn!("<<< GoAway received, time left: {:?}", time_left);
+                        None
                     }
                     _ => None,
                 };
@@ -93,7 +257,7 @@ pub async fn run(
                 }
             }
             Err(e) => {
-                error!("Gemini API error: {:?}", e);
+                error!("Live model error: {:?}", e);
                 if tx_in.send(WsInbound::Error(format!("{:?}", e))).is_err() {
                     break;
                 }
@@ -102,4 +266,44 @@ pub async fn run(
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Move outbound media and tool results into the send queue until the
+/// media channel closes.
+async fn fill_queue(
+    mut rx_out: UnboundedReceiver<WsOutbound>,
+    mut tool_rx: UnboundedReceiver<WsOutbound>,
+    queue: Arc<SharedSendQueue>,
+    video_paused: Arc<AtomicBool>,
+) {
+    loop {
+        let msg = tokio::select! {
+            msg = rx_out.recv() => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+            Some(msg) = tool_rx.recv() => msg,
+        };
+
+        if matches!(msg, WsOutbound::Video(_)) && video_paused.load(Ordering::Relaxed) {
+            debug!("Dropping video frame - token budget exceeded");
+            continue;
+        }
+        queue.push(msg);
+    }
+    queue.close();
+}
+
+/// Reconnect after the socket dropped, reporting failure to the UI as fatal.
+///
+/// Returns false if the client gave up and the writer should stop.
+async fn reconnect(backend: &mut dyn LiveModelBackend, tx_status: &UnboundedSender<WsInbound>) -> bool {
+    match backend.reconnect().await {
+        Ok(()) => true,
+        Err(e) => {
+            error!("Could not reconnect to {}: {}", backend.name(), e);
+            let _ = tx_status.send(WsInbound::Fatal(format!("Reconnect failed: {}", e)));
+            false
+        }
+    }
+}
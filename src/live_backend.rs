@@ -0,0 +1,276 @@
+//! Backend-agnostic interface to a realtime model session
+//!
+//! The turn FSM speaks in [`WsOutbound`] messages and the rest of the app
+//! consumes [`ApiResponse`] events; a [`LiveModelBackend`] translates between
+//! those and one provider's wire protocol. [`GeminiClient`] is the Gemini Live
+//! implementation, [`crate::openai_realtime::OpenAiRealtimeClient`] speaks the
+//! OpenAI Realtime protocol.
+//!
+//! [`SessionSetup`] holds the settings a prompt profile controls; backends
+//! apply it when connecting and again whenever the profile is switched.
+
+use crate::gemini::{
+    ActivityHandling, ApiResponse, ClientContent, FunctionDeclaration, GeminiClientConfig,
+    MediaResolution, RealtimeInputConfig, Result, ToolDeclaration, ToolResponse,
+};
+use crate::gemini_client::{ConnectionEvent, GeminiClient};
+use crate::media_event::WsOutbound;
+use futures_util::future::BoxFuture;
+use tokio::sync::mpsc;
+
+/// Instructions, sampling and tools of a session
+#[derive(Debug, Clone, Default)]
+pub struct SessionSetup {
+    pub instructions: String,
+    /// `None` leaves the provider's default
+    pub temperature: Option<f32>,
+    /// `None` leaves the provider's default
+    pub media_resolution: Option<MediaResolution>,
+    /// Client-side functions the model may call
+    pub tools: Vec<FunctionDeclaration>,
+    /// Gemini's server-side code execution
+    pub code_execution: bool,
+    /// Gemini's Google Search grounding
+    pub google_search: bool,
+}
+
+impl GeminiClientConfig {
+    /// Take over the settings of `setup`
+    pub fn apply_setup(&mut self, setup: SessionSetup) {
+        self.system_instruction = Some(setup.instructions);
+        self.temperature = setup.temperature;
+        self.media_resolution = setup.media_resolution;
+        self.tools = if setup.tools.is_empty() {
+            Vec::new()
+        } else {
+            vec![ToolDeclaration {
+                function_declarations: setup.tools,
+                ..Default::default()
+            }]
+        };
+        self.code_execution = setup.code_execution;
+        self.google_search = setup.google_search;
+    }
+}
+
+/// A live, bidirectional model session
+pub trait LiveModelBackend: Send {
+    /// Provider name for logs
+    fn name(&self) -> &'static str;
+
+    /// Connect and set up the session
+    fn connect(&mut self) -> BoxFuture<'_, Result<()>>;
+
+    /// Take the receiver of typed server events; it stays valid across reconnects
+    fn subscribe(&mut self) -> mpsc::Receiver<Result<ApiResponse>>;
+
+    /// The user started a turn
+    fn activity_start(&mut self) -> BoxFuture<'_, Result<()>>;
+
+    /// The user finished a turn and expects an answer
+    fn activity_end(&mut self) -> BoxFuture<'_, Result<()>>;
+
+    /// 16kHz mono 16-bit PCM
+    fn send_audio(&mut self, pcm: Vec<u8>) -> BoxFuture<'_, Result<()>>;
+
+    /// JPEG screen frame
+    fn send_image(&mut self, jpeg: Vec<u8>) -> BoxFuture<'_, Result<()>>;
+
+    /// Typed user input
+    fn send_text(&mut self, text: String) -> BoxFuture<'_, Result<()>>;
+
+    /// Results of function calls the model asked for
+    fn send_tool_response(&mut self, response: ToolResponse) -> BoxFuture<'_, Result<()>>;
+
+    /// Turns to add to the conversation history
+    fn send_context(&mut self, content: ClientContent) -> BoxFuture<'_, Result<()>>;
+
+    /// Whether new user activity cuts off the response in progress
+    fn set_activity_handling(&mut self, mode: ActivityHandling) -> BoxFuture<'_, Result<()>>;
+
+    /// Wait until the connection needs attention
+    fn connection_lost(&mut self) -> BoxFuture<'_, ConnectionEvent>;
+
+    /// Replace a dropped or unresponsive connection
+    fn reconnect(&mut self) -> BoxFuture<'_, Result<()>>;
+
+    /// Switch the session to new settings, reconnecting if the provider needs to
+    fn apply_setup(&mut self, setup: SessionSetup) -> BoxFuture<'_, Result<()>>;
+
+    /// The server announced it will close the session soon
+    fn session_ending(&mut self) -> BoxFuture<'_, Result<()>> {
+        self.reconnect()
+    }
+
+    /// Send an outbound message from the turn FSM
+    fn send(&mut self, msg: WsOutbound) -> BoxFuture<'_, Result<()>> {
+        match msg {
+            WsOutbound::ActivityStart => self.activity_start(),
+            WsOutbound::ActivityEnd => self.activity_end(),
+            WsOutbound::Audio(pcm) => self.send_audio(pcm),
+            WsOutbound::Video(jpeg) => self.send_image(jpeg),
+            WsOutbound::Text(text) => self.send_text(text),
+            WsOutbound::ToolResponse(response) => self.send_tool_response(response),
+            WsOutbound::ClientContent(content) => self.send_context(content),
+            WsOutbound::ConfigUpdate(RealtimeInputConfig {
+                activity_handling: Some(mode),
+                ..
+            }) => self.set_activity_handling(mode),
+            WsOutbound::ConfigUpdate(config) => {
+                tracing::debug!("{} ignores realtime input config {:?}", self.name(), config);
+                Box::pin(async { Ok(()) })
+            }
+        }
+    }
+}
+
+impl LiveModelBackend for GeminiClient {
+    fn name(&self) -> &'static str {
+        "Gemini"
+    }
+
+    fn connect(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.connect_and_setup())
+    }
+
+    fn subscribe(&mut self) -> mpsc::Receiver<Result<ApiResponse>> {
+        GeminiClient::subscribe(self)
+    }
+
+    fn activity_start(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.send_outbound(WsOutbound::ActivityStart))
+    }
+
+    fn activity_end(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.send_outbound(WsOutbound::ActivityEnd))
+    }
+
+    fn send_audio(&mut self, pcm: Vec<u8>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.send_outbound(WsOutbound::Audio(pcm)))
+    }
+
+    fn send_image(&mut self, jpeg: Vec<u8>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.send_outbound(WsOutbound::Video(jpeg)))
+    }
+
+    fn send_text(&mut self, text: String) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.send_outbound(WsOutbound::Text(text)))
+    }
+
+    fn send_tool_response(&mut self, response: ToolResponse) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.send_outbound(WsOutbound::ToolResponse(response)))
+    }
+
+    fn send_context(&mut self, content: ClientContent) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.send_outbound(WsOutbound::ClientContent(content)))
+    }
+
+    fn set_activity_handling(&mut self, mode: ActivityHandling) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.send_outbound(WsOutbound::ConfigUpdate(RealtimeInputConfig {
+            activity_handling: Some(mode),
+            ..Default::default()
+        })))
+    }
+
+    fn connection_lost(&mut self) -> BoxFuture<'_, ConnectionEvent> {
+        Box::pin(GeminiClient::connection_lost(self))
+    }
+
+    fn reconnect(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(GeminiClient::reconnect(self))
+    }
+
+    // The setup message is only read when a session starts
+    fn apply_setup(&mut self, setup: SessionSetup) -> BoxFuture<'_, Result<()>> {
+        let mut config = self.config().clone();
+        config.apply_setup(setup);
+        Box::pin(self.reconfigure(config))
+    }
+
+    // Keep serving on the old session while a standby is set up
+    fn session_ending(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.prepare_standby())
+    }
+
+    // The whole config update goes through, not just the activity handling
+    fn send(&mut self, msg: WsOutbound) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.send_outbound(msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gemini::GeminiClientConfig;
+    use crate::mock_live::{MockLiveServer, Reply, Script, Trigger};
+    use std::time::Duration;
+
+    #[test]
+    fn test_apply_setup_to_gemini_config() {
+        let mut config = GeminiClientConfig::default();
+        config.apply_setup(SessionSetup {
+            instructions: "Read along".to_string(),
+            media_resolution: Some(MediaResolution::High),
+            tools: vec![FunctionDeclaration {
+                name: "current_time".to_string(),
+                description: "Now".to_string(),
+                parameters: None,
+                behavior: None,
+            }],
+            google_search: true,
+            ..Default::default()
+        });
+
+        assert_eq!(config.system_instruction.as_deref(), Some("Read along"));
+        assert_eq!(config.temperature, None);
+        assert_eq!(config.media_resolution, Some(MediaResolution::High));
+        assert_eq!(config.tools.len(), 1);
+        assert_eq!(config.tools[0].function_declarations[0].name, "current_time");
+        assert!(config.google_search);
+        assert!(!config.code_execution);
+
+        config.apply_setup(SessionSetup::default());
+        assert!(config.tools.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_gemini_client_as_backend() {
+        let server =
+            MockLiveServer::start(Script::new().on(Trigger::ActivityEnd, Reply::Text("hi".into())))
+                .await;
+        let mut backend: Box<dyn LiveModelBackend> = Box::new(GeminiClient::new(GeminiClientConfig {
+            url: server.url(),
+            ..Default::default()
+        }));
+        backend.connect().await.unwrap();
+        let mut rx = backend.subscribe();
+
+        backend.activity_start().await.unwrap();
+        backend.send_audio(vec![0; 640]).await.unwrap();
+        backend
+            .set_activity_handling(ActivityHandling::NoInterruption)
+            .await
+            .unwrap();
+        backend.activity_end().await.unwrap();
+
+        let text = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(Ok(ApiResponse::TextResponse { text, .. })) = rx.recv().await {
+                    return text;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(text, "hi");
+
+        let received = server.received();
+        assert!(received[1]["realtimeInput"].get("activityStart").is_some());
+        assert_eq!(received[2]["realtimeInput"]["audio"]["mimeType"], "audio/pcm;rate=16000");
+        assert_eq!(
+            received[3]["setup"]["realtimeInputConfig"]["activityHandling"],
+            "NO_INTERRUPTION"
+        );
+        assert!(received[4]["realtimeInput"].get("activityEnd").is_some());
+    }
+}
@@ -2,6 +2,7 @@
 
 mod media_event;
 mod media_in;
+mod media_out;
 mod simple_turn_fsm;
 mod simple_turn_runner;
 mod gemini_ws_unified;
@@ -10,10 +11,23 @@ mod recorder;
 // Keep existing modules we still need
 mod gemini;
 mod gemini_client;
+mod live_backend;
+#[cfg(test)]
+mod mock_live;
+mod openai_realtime;
+mod profile;
+mod response_filter;
 mod screen;
+mod send_queue;
+mod session_context;
 mod audio_seg;
+mod auth;
+mod escalation;
+mod tools;
 mod ui;
+mod usage;
 mod util;
+mod wire_trace;
 
 use media_event::{MediaEvent, WsOutbound, WsInbound, Outgoing};
 use audio_seg::{AudioSegmenter, SegConfig};
@@ -21,8 +35,8 @@ use ui::{launch_ui, AudioSample, ConversationEntry};
 
 use anyhow::Result;
 use clap::{Parser, ValueEnum};
-use tokio::sync::{broadcast, mpsc};
-use tracing::{error, info};
+use tokio::sync::{broadcast, mpsc, watch};
+use tracing::{error, info, warn};
 use std::sync::Arc;
 use std::sync::atomic::AtomicU64;
 use std::time::Instant;
@@ -37,6 +51,90 @@ struct Args {
     /// Enable test recorder (writes turns/frames to ./recordings/)
     #[arg(long, help = "Enable test recorder (writes turns/frames to ./recordings/)")]
     record: bool,
+
+    /// Prompt profile: instructions, temperature, media resolution, frame cadence and tools
+    #[arg(long, default_value = profile::DEFAULT_PROFILE)]
+    profile: String,
+
+    /// Directory holding `<name>.json` profiles
+    #[arg(long, value_name = "DIR", default_value = "profiles")]
+    profile_dir: std::path::PathBuf,
+
+    /// Realtime model provider to talk to
+    #[arg(long, value_enum, default_value = "gemini")]
+    backend: BackendArg,
+
+    /// How the model should respond
+    #[arg(long, value_enum, default_value = "text")]
+    response_modality: ResponseModalityArg,
+
+    /// Notes, a summary or a saved session to seed the session with (repeatable)
+    #[arg(long = "context", value_name = "FILE")]
+    context_files: Vec<std::path::PathBuf>,
+
+    /// Save the conversation here on exit, to pass back with --context next time
+    #[arg(long, value_name = "FILE")]
+    save_session: Option<std::path::PathBuf>,
+
+    /// Voice for audio responses, e.g. Kore or Puck (Gemini), alloy or verse (OpenAI)
+    #[arg(long)]
+    voice: Option<String>,
+
+    /// Language of audio responses as a BCP-47 code, e.g. de-DE
+    #[arg(long)]
+    language: Option<String>,
+
+    /// Total tokens the session may use before the budget action applies
+    #[arg(long)]
+    token_budget: Option<u64>,
+
+    /// Let Gemini write and run code server-side
+    #[arg(long)]
+    code_execution: bool,
+
+    /// Let Gemini ground answers with Google Search
+    #[arg(long)]
+    google_search: bool,
+
+    /// Command printing an ephemeral token to connect with instead of GEMINI_API_KEY
+    #[arg(long, conflicts_with_all = ["bearer_token_command", "vertex_project"])]
+    ephemeral_token_command: Option<String>,
+
+    /// Command printing an OAuth access token, e.g. `gcloud auth print-access-token`
+    #[arg(long)]
+    bearer_token_command: Option<String>,
+
+    /// Route the session through Vertex AI in this Google Cloud project
+    #[arg(long)]
+    vertex_project: Option<String>,
+
+    /// Vertex AI region, or `global`
+    #[arg(long, default_value = "us-central1", requires = "vertex_project")]
+    vertex_location: String,
+
+    /// Live model to use instead of the default
+    #[arg(long)]
+    model: Option<String>,
+
+    /// What to do when the token budget is exceeded
+    #[arg(long, value_enum, default_value = "warn")]
+    budget_action: BudgetActionArg,
+
+    /// Chat-completions URL to escalate hard questions to, with ESCALATION_API_KEY as bearer token
+    #[arg(long)]
+    escalation_url: Option<String>,
+
+    /// Model answering escalated questions
+    #[arg(long, default_value = "gpt-4o", requires = "escalation_url")]
+    escalation_model: String,
+
+    /// Write every frame exchanged with Gemini to this JSONL file
+    #[arg(long, value_name = "FILE")]
+    wire_trace: Option<std::path::PathBuf>,
+
+    /// Also save the audio and image payloads left out of the trace here
+    #[arg(long, value_name = "DIR", requires = "wire_trace")]
+    wire_trace_payloads: Option<std::path::PathBuf>,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -59,6 +157,64 @@ impl From<AudioSourceArg> for media_in::AudioSource {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BackendArg {
+    /// Gemini Live API, or Vertex AI with --vertex-project
+    Gemini,
+    /// OpenAI Realtime API, authenticated with OPENAI_API_KEY
+    Openai,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ResponseModalityArg {
+    /// Stream text into the overlay
+    Text,
+    /// Speak responses through the default audio output
+    Audio,
+}
+
+impl From<ResponseModalityArg> for gemini::ResponseModality {
+    fn from(arg: ResponseModalityArg) -> Self {
+        match arg {
+            ResponseModalityArg::Text => gemini::ResponseModality::Text,
+            ResponseModalityArg::Audio => gemini::ResponseModality::Audio,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum BudgetActionArg {
+    /// Log a warning and keep going
+    Warn,
+    /// Stop sending screen frames
+    PauseVideo,
+}
+
+impl From<BudgetActionArg> for usage::BudgetAction {
+    fn from(arg: BudgetActionArg) -> Self {
+        match arg {
+            BudgetActionArg::Warn => usage::BudgetAction::Warn,
+            BudgetActionArg::PauseVideo => usage::BudgetAction::PauseVideo,
+        }
+    }
+}
+
+/// Session settings for `profile`, with the built-in tools forced on by flags
+fn session_setup(
+    profile: &profile::Profile,
+    tools: &tools::ToolRegistry,
+    code_execution: bool,
+    google_search: bool,
+) -> live_backend::SessionSetup {
+    let mut setup = profile.session_setup(tools);
+    setup.code_execution |= code_execution;
+    setup.google_search |= google_search;
+    if setup.tools.iter().any(|tool| tool.name == escalation::TOOL_NAME) {
+        setup.instructions = format!("{}\n{}", setup.instructions, escalation::INSTRUCTION);
+    }
+    setup
+}
+
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> Result<()> {
     let args = Args::parse();
@@ -77,9 +233,11 @@ async fn main() -> Result<()> {
     
     info!("Starting RhoLive - Refactored Architecture");
     
-    // Get API key
-    let api_key = std::env::var("GEMINI_API_KEY")
-        .expect("GEMINI_API_KEY environment variable must be set");
+    // Function tools are registered up front so profiles can be checked against them
+    let mut tool_registry = tools::ToolRegistry::new();
+    tool_registry.register(tools::CurrentTime);
+    let profile = profile::load(&args.profile_dir, &args.profile, &tool_registry)?;
+    info!("Using profile {}", profile.name);
     
     // === Layer 1: Media Capture ===
     // Single broadcast channel for all media events
@@ -104,16 +262,36 @@ async fn main() -> Result<()> {
     // ===== Launch UI =====
     info!("Starting UI...");
     let ui_state = launch_ui();
+    let (profile_tx, mut profile_rx) = mpsc::unbounded_channel::<String>();
     
     if let Ok(mut state) = ui_state.lock() {
+        state.profiles = profile::list(&args.profile_dir);
+        state.active_profile = profile.name.clone();
+        state.profile_requests = Some(profile_tx);
         state.connected = true;
-        state.status_message = "Connected to Gemini".to_string();
+        state.status_message = match args.backend {
+            BackendArg::Gemini => "Connected to Gemini".to_string(),
+            BackendArg::Openai => "Connected to OpenAI Realtime".to_string(),
+        };
     }
     
     // ===== Layer 1: Media Capture =====
     info!("Starting media capture with audio source: {:?}", args.audio_source);
     media_in::spawn_audio_capture_with_source(media_tx.clone(), args.audio_source.into())?;
-    media_in::spawn_video_capture(media_tx.clone())?;
+    let (frame_interval_tx, frame_interval_rx) = watch::channel(profile.frame_interval());
+    media_in::spawn_video_capture(media_tx.clone(), frame_interval_rx)?;
+    
+    // ===== Audio Playback =====
+    let response_modality: gemini::ResponseModality = args.response_modality.into();
+    let playback_tx = match response_modality {
+        gemini::ResponseModality::Audio => {
+            if !matches!(args.audio_source, AudioSourceArg::Mic) {
+                warn!("System audio capture will also pick up spoken responses; consider --audio-source mic");
+            }
+            Some(media_out::spawn_audio_playback())
+        }
+        gemini::ResponseModality::Text => None,
+    };
     
     // ===== Audio Segmentation Task =====
     // This bridges Layer 1 -> Layer 2
@@ -133,6 +311,7 @@ async fn main() -> Result<()> {
     let turn_id_gen_seg = turn_id_generator.clone();
     let ui_conv_tx_seg = ui_conv_tx.clone();
     let ui_state_seg = ui_state.clone();
+    let playback_tx_seg = playback_tx.clone();
     
     // Run segmenter in a dedicated thread
     std::thread::spawn(move || {
@@ -146,6 +325,10 @@ async fn main() -> Result<()> {
         let outgoing_tx_forward = outgoing_tx_seg.clone();
         std::thread::spawn(move || {
             while let Ok(event) = sync_outgoing_rx.recv() {
+                // Stop talking as soon as the user starts
+                if let (Outgoing::ActivityStart(_), Some(playback)) = (&event, &playback_tx_seg) {
+                    let _ = playback.send(media_out::PlaybackCommand::Flush);
+                }
                 let _ = outgoing_tx_forward.send(event);
             }
         });
@@ -189,12 +372,28 @@ async fn main() -> Result<()> {
         }
     });
     
+    // ===== Escalation to a stronger model =====
+    let escalator = match &args.escalation_url {
+        Some(url) => {
+            let config = escalation::EscalationConfig {
+                url: url.clone(),
+                model: args.escalation_model.clone(),
+                api_key: std::env::var("ESCALATION_API_KEY").ok(),
+                ..Default::default()
+            };
+            let context = escalation::EscalationContext::default();
+            Some(Arc::new(escalation::Escalator::new(config, context, ws_in_tx.clone())?))
+        }
+        None => None,
+    };
+    
     // ===== Layer 2: Simple Turn FSM =====
     info!("Starting Simple Turn FSM...");
     let media_tx_fsm = media_tx.clone();
     let media_rx_fsm = media_tx.subscribe();
     let (ws_in_fsm_tx, ws_in_rx_fsm) = mpsc::unbounded_channel::<WsInbound>();
     let record_flag = args.record;
+    let escalation_context = escalator.as_ref().map(|escalator| escalator.context().clone());
     
     tokio::spawn(async move {
         simple_turn_runner::run(
@@ -204,14 +403,135 @@ async fn main() -> Result<()> {
             ws_out_tx,
             ws_in_rx_fsm,
             record_flag,
+            escalation_context,
         ).await;
     });
     
-    // ===== Layer 3: Gemini WebSocket =====
-    info!("Starting Gemini connection...");
+    // ===== Layer 3: Live model WebSocket =====
+    info!("Starting {:?} connection...", args.backend);
+    if let Some(escalator) = &escalator {
+        tool_registry.register(escalation::EscalateTool(escalator.clone()));
+    }
+    let setup = session_setup(&profile, &tool_registry, args.code_execution, args.google_search);
+    let token_budget = args.token_budget.map(|max_total_tokens| usage::TokenBudget {
+        max_total_tokens,
+        action: args.budget_action.into(),
+    });
+    let mut initial_context = Vec::new();
+    for path in &args.context_files {
+        let turns = session_context::load(path)?;
+        info!("Loaded {} context turns from {}", turns.len(), path.display());
+        initial_context.extend(turns.into_iter().map(gemini::Content::from));
+    }
+    let mut wire_tracer = None;
+    let live_model: Box<dyn live_backend::LiveModelBackend> = match args.backend {
+        BackendArg::Gemini => {
+            // Pick credentials: a token command if given, otherwise the API key
+            let auth: Arc<dyn auth::AuthProvider> = if let Some(command) = &args.ephemeral_token_command {
+                Arc::new(auth::EphemeralTokenCommand::new(command.clone()))
+            } else if args.bearer_token_command.is_some() || args.vertex_project.is_some() {
+                // Vertex AI only takes OAuth tokens; gcloud access tokens last an hour
+                let command = args
+                    .bearer_token_command
+                    .clone()
+                    .unwrap_or_else(|| "gcloud auth print-access-token".to_string());
+                Arc::new(auth::BearerTokenCommand::new(command, std::time::Duration::from_secs(3600)))
+            } else {
+                let api_key = std::env::var("GEMINI_API_KEY")
+                    .expect("GEMINI_API_KEY environment variable must be set");
+                Arc::new(auth::ApiKey(api_key))
+            };
+
+            let backend = match &args.vertex_project {
+                Some(project) => gemini::Backend::VertexAi {
+                    project: project.clone(),
+                    location: args.vertex_location.clone(),
+                },
+                None => gemini::Backend::GeminiApi,
+            };
+            let speech = match (&args.voice, &args.language) {
+                (None, None) => None,
+                (voice, language) => Some(gemini::SpeechConfig::new(voice.as_deref(), language.as_deref())?),
+            };
+            let mut gemini_config = gemini::GeminiClientConfig {
+                backend,
+                response_modality,
+                speech,
+                initial_context,
+                ..Default::default()
+            };
+            gemini_config.apply_setup(setup);
+            if let Some(model) = &args.model {
+                gemini_config.model = model.clone();
+            }
+            let mut client = gemini_client::GeminiClient::with_auth(auth, Some(gemini_config));
+            if let Some(path) = &args.wire_trace {
+                let tracer = wire_trace::WireTracer::create(path, args.wire_trace_payloads.clone())?;
+                client.set_tracer(tracer.clone());
+                wire_tracer = Some(tracer);
+                info!("Tracing wire traffic to {}", path.display());
+            }
+            Box::new(client)
+        }
+        BackendArg::Openai => {
+            if args.language.is_some() || args.code_execution || args.google_search || args.wire_trace.is_some() {
+                warn!("--language, --code-execution, --google-search and --wire-trace only apply to Gemini, ignoring them");
+            }
+            let api_key = std::env::var("OPENAI_API_KEY")
+                .expect("OPENAI_API_KEY environment variable must be set");
+            let mut openai_config = openai_realtime::OpenAiRealtimeConfig {
+                response_modality,
+                voice: args.voice.clone(),
+                initial_context,
+                ..Default::default()
+            };
+            openai_config.apply_setup(setup);
+            if let Some(model) = &args.model {
+                openai_config.model = model.clone();
+            }
+            Box::new(openai_realtime::OpenAiRealtimeClient::new(api_key, openai_config))
+        }
+    };
+    let (setup_tx, setup_rx) = mpsc::unbounded_channel::<live_backend::SessionSetup>();
+    let profile_tools = tool_registry.clone();
+    tokio::spawn(async move {
+        if let Err(e) = gemini_ws_unified::run(
+            live_model,
+            ws_out_rx,
+            ws_in_tx,
+            tool_registry,
+            token_budget,
+            escalator,
+            setup_rx,
+        ).await {
+            error!("Live model WebSocket error: {}", e);
+        }
+    });
+    
+    // Profile switches from the UI reconnect with the new setup
+    let ui_state_profile = ui_state.clone();
+    let profile_dir = args.profile_dir.clone();
+    let (code_execution, google_search) = (args.code_execution, args.google_search);
     tokio::spawn(async move {
-        if let Err(e) = gemini_ws_unified::run(&api_key, ws_out_rx, ws_in_tx).await {
-            error!("Gemini WebSocket error: {}", e);
+        while let Some(name) = profile_rx.recv().await {
+            // Read the file again so edits apply without a restart
+            match profile::load(&profile_dir, &name, &profile_tools) {
+                Ok(profile) => {
+                    info!("Switching to profile {}", profile.name);
+                    let _ = frame_interval_tx.send(profile.frame_interval());
+                    let _ = setup_tx.send(session_setup(&profile, &profile_tools, code_execution, google_search));
+                    if let Ok(mut state) = ui_state_profile.lock() {
+                        state.status_message = format!("Profile: {}", profile.name);
+                        state.active_profile = profile.name;
+                    }
+                }
+                Err(e) => {
+                    error!("Could not switch profile: {:#}", e);
+                    if let Ok(mut state) = ui_state_profile.lock() {
+                        state.status_message = format!("Could not load profile {}", name);
+                    }
+                }
+            }
         }
     });
     
@@ -249,19 +569,28 @@ async fn main() -> Result<()> {
         while let Some(entry) = ui_conv_rx.recv().await {
             if let Ok(mut state) = ui_state_conv.lock() {
                 // Check if we should update the last entry or add a new one
-                if entry.is_streaming && entry.role == "Gemini" {
-                    // Look for an existing streaming Gemini entry to update
+                if entry.role != "User" {
+                    // Look for an existing streaming entry of the same kind to update or finish
                     if let Some(last_entry) = state.conversation_history.back_mut() {
-                        if last_entry.role == "Gemini" && last_entry.is_streaming {
+                        if last_entry.role == entry.role && last_entry.is_streaming && entry.text.is_empty() {
+                            // A filtered response withdraws what was streamed of it
+                            state.conversation_history.pop_back();
+                            continue;
+                        }
+                        if last_entry.role == entry.role && last_entry.is_streaming {
                             // Update the existing streaming entry
                             last_entry.text = entry.text;
                             last_entry.timestamp = entry.timestamp;
+                            last_entry.is_streaming = entry.is_streaming;
                             continue;
                         }
                     }
                 }
                 
                 // Add new entry
+                if entry.text.is_empty() {
+                    continue;
+                }
                 state.conversation_history.push_back(entry);
                 while state.conversation_history.len() > 50 {
                     state.conversation_history.pop_front();
@@ -272,8 +601,17 @@ async fn main() -> Result<()> {
     
     // WebSocket event forwarder and UI handler
     let ui_conv_tx_resp = ui_conv_tx.clone();
+    let ui_state_resp = ui_state.clone();
     tokio::spawn(async move {
-        let mut current_text = String::new();
+        let mut responses = response_filter::ResponsePipeline::new(&[
+            response_filter::NOTHING,
+            escalation::ESCALATE_MARKER,
+        ])
+        .with_filter(response_filter::DropRepeats::default());
+        let mut current_code = String::new();
+        let mut escalation_text = String::new();
+        let mut pending_sources: Option<String> = None;
+        let mut transcript_done = false;
         
         while let Some(event) = ws_in_rx.recv().await {
             // Forward to FSM
@@ -281,28 +619,142 @@ async fn main() -> Result<()> {
             
             // Handle UI updates
             match event {
-                WsInbound::Text { content, is_final } => {
-                    current_text.push_str(&content);
+                // Spoken responses arrive as output transcriptions instead of text
+                WsInbound::Text { content, is_final }
+                | WsInbound::OutputTranscription { text: content, is_final } => {
+                    // Sentinels are hidden while streaming; the filters run once the text is final
+                    if let Some(text) = responses.push(&content) {
+                        let _ = ui_conv_tx_resp.send(ConversationEntry {
+                            role: "Gemini".to_string(),
+                            text,
+                            timestamp: Instant::now(),
+                            is_streaming: true,
+                        });
+                    }
                     
-                    // Remove any <nothing> responses from the accumulated text
-                    if current_text.contains("<nothing>") {
-                        current_text = current_text.replace("<nothing>", "");
+                    if is_final {
+                        finish_response(&mut responses, "", &ui_conv_tx_resp);
+                    }
+                }
+                WsInbound::InputTranscription { text, .. } => {
+                    // Show what the server heard, starting fresh for each new turn
+                    if let Ok(mut state) = ui_state_resp.lock() {
+                        if transcript_done {
+                            state.current_transcript.clear();
+                            transcript_done = false;
+                        }
+                        state.current_transcript.push_str(&text);
                     }
+                }
+                WsInbound::CodeExecution { language, code } => {
+                    // Close the text so far; the code gets its own block below it
+                    finish_response(&mut responses, "", &ui_conv_tx_resp);
+                    current_code = format!("```{}\n{}\n```", language.to_lowercase(), code.trim_end());
+                    let _ = ui_conv_tx_resp.send(ConversationEntry {
+                        role: "Code".to_string(),
+                        text: current_code.clone(),
+                        timestamp: Instant::now(),
+                        is_streaming: true, // Completed by the execution result
+                    });
+                }
+                WsInbound::CodeExecutionResult { outcome, output } => {
+                    let label = if outcome == "OUTCOME_OK" { "output" } else { outcome.as_str() };
+                    current_code.push_str(&format!("\n```{}\n{}\n```", label, output.trim_end()));
+                    let _ = ui_conv_tx_resp.send(ConversationEntry {
+                        role: "Code".to_string(),
+                        text: std::mem::take(&mut current_code),
+                        timestamp: Instant::now(),
+                        is_streaming: false,
+                    });
+                }
+                WsInbound::Sources { queries, sources } => {
+                    // Shown once the answer is finished so it does not split the response
+                    pending_sources = Some(format_sources(&queries, &sources));
+                }
+                done @ (WsInbound::GenerationComplete | WsInbound::TurnComplete) => {
+                    transcript_done = true;
                     
-                    let trimmed = current_text.trim();
+                    // Finalize a response that never got an explicit final chunk
+                    finish_response(&mut responses, "", &ui_conv_tx_resp);
+                    if matches!(done, WsInbound::TurnComplete) {
+                        count_turn(responses.end_turn(), &ui_state_resp);
+                    }
                     
-                    // Only send to UI if not empty after cleaning
-                    if !trimmed.is_empty() {
+                    if let Some(text) = pending_sources.take() {
                         let _ = ui_conv_tx_resp.send(ConversationEntry {
-                            role: "Gemini".to_string(),
-                            text: trimmed.to_string(),
+                            role: "Sources".to_string(),
+                            text,
                             timestamp: Instant::now(),
-                            is_streaming: !is_final, // Mark as streaming if not final
+                            is_streaming: false,
                         });
                     }
+                }
+                WsInbound::Interrupted => {
+                    transcript_done = true;
                     
+                    // Stop speaking and close the half-written entry
+                    if let Some(playback) = &playback_tx {
+                        let _ = playback.send(media_out::PlaybackCommand::Flush);
+                    }
+                    finish_response(&mut responses, " …", &ui_conv_tx_resp);
+                    count_turn(responses.end_turn(), &ui_state_resp);
+                }
+                WsInbound::Escalation { content, is_final } => {
+                    // Streams into its own entry below the live answer
+                    escalation_text.push_str(&content);
+                    if !escalation_text.trim().is_empty() {
+                        let _ = ui_conv_tx_resp.send(ConversationEntry {
+                            role: "Escalation".to_string(),
+                            text: escalation_text.trim().to_string(),
+                            timestamp: Instant::now(),
+                            is_streaming: !is_final,
+                        });
+                    }
                     if is_final {
-                        current_text.clear();
+                        escalation_text.clear();
+                    }
+                }
+                WsInbound::Usage { turn, session } => {
+                    if let Ok(mut state) = ui_state_resp.lock() {
+                        state.turn_tokens = turn.total;
+                        state.session_tokens = session.total;
+                    }
+                }
+                WsInbound::SendQueue(stats) => {
+                    if let Ok(mut state) = ui_state_resp.lock() {
+                        state.send_queue_depth = stats.depth;
+                        state.media_dropped = stats.lost();
+                    }
+                }
+                WsInbound::TokenBudgetExceeded(action) => {
+                    if let Ok(mut state) = ui_state_resp.lock() {
+                        state.status_message = match action {
+                            usage::BudgetAction::Warn => "Token budget exceeded".to_string(),
+                            usage::BudgetAction::PauseVideo => {
+                                "Token budget exceeded - screen sharing paused".to_string()
+                            }
+                        };
+                    }
+                }
+                WsInbound::ContextCompressed { before_tokens, after_tokens } => {
+                    if let Ok(mut state) = ui_state_resp.lock() {
+                        state.status_message = format!(
+                            "Context compressed ({}k → {}k tokens)",
+                            before_tokens / 1000,
+                            after_tokens / 1000
+                        );
+                    }
+                }
+                WsInbound::Fatal(message) => {
+                    if let Ok(mut state) = ui_state_resp.lock() {
+                        state.connected = false;
+                        state.status_message = format!("⚠ {}", message);
+                    }
+                }
+                WsInbound::Audio(pcm) => {
+                    let muted = ui_state_resp.lock().map(|s| s.is_muted).unwrap_or(false);
+                    if let (Some(playback), false) = (&playback_tx, muted) {
+                        let _ = playback.send(media_out::PlaybackCommand::Play(pcm));
                     }
                 }
                 _ => {}
@@ -314,5 +766,78 @@ async fn main() -> Result<()> {
     tokio::signal::ctrl_c().await?;
     info!("Shutting down...");
     
+    // Frames still queued for the trace writer would be lost on exit
+    if let Some(tracer) = &wire_tracer {
+        tracer.flush();
+    }
+    
+    if let Some(path) = &args.save_session {
+        let turns = ui_state
+            .lock()
+            .map(|state| saved_turns(&state.conversation_history))
+            .unwrap_or_default();
+        match session_context::save(path, &turns) {
+            Ok(()) => info!("Saved {} turns to {}", turns.len(), path.display()),
+            Err(e) => error!("Could not save session: {:#}", e),
+        }
+    }
+    
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Conversation turns worth remembering; search sources are left out.
+fn saved_turns<'a>(
+    history: impl IntoIterator<Item = &'a ConversationEntry>,
+) -> Vec<session_context::ContextTurn> {
+    history
+        .into_iter()
+        .filter_map(|entry| match entry.role.as_str() {
+            "User" => Some(session_context::ContextTurn::user(entry.text.clone())),
+            "Gemini" | "Code" | "Escalation" => Some(session_context::ContextTurn::model(entry.text.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Send the filtered response as a finished conversation entry, or an empty
+/// one withdrawing its streaming entry if the filters dropped it.
+fn finish_response(
+    responses: &mut response_filter::ResponsePipeline,
+    suffix: &str,
+    ui_conv_tx: &mpsc::UnboundedSender<ConversationEntry>,
+) {
+    let text = match responses.finish() {
+        Some(text) => format!("{}{}", text, suffix),
+        None => String::new(),
+    };
+    let _ = ui_conv_tx.send(ConversationEntry {
+        role: "Gemini".to_string(),
+        text,
+        timestamp: Instant::now(),
+        is_streaming: false,
+    });
+}
+
+/// Tally a finished turn as answered or silent.
+fn count_turn(outcome: Option<response_filter::TurnOutcome>, ui_state: &Arc<std::sync::Mutex<ui::UiState>>) {
+    let Some(outcome) = outcome else { return };
+    info!("Turn ended {:?}", outcome);
+    if let Ok(mut state) = ui_state.lock() {
+        match outcome {
+            response_filter::TurnOutcome::Answered => state.answered_turns += 1,
+            response_filter::TurnOutcome::Silent => state.silent_turns += 1,
+        }
+    }
+}
+
+/// One line per search query and per source; sources end with their URL.
+fn format_sources(queries: &[String], sources: &[gemini::WebSource]) -> String {
+    let mut lines = Vec::new();
+    if !queries.is_empty() {
+        lines.push(format!("🔍 {}", queries.join(" · ")));
+    }
+    for source in sources {
+        lines.push(format!("{} {}", source.title, source.uri).trim().to_string());
+    }
+    lines.join("\n")
+}
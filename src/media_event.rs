@@ -1,5 +1,8 @@
 //! Unified media event types for the refactored architecture
 
+use crate::gemini::{ClientContent, RealtimeInputConfig, ToolResponse, WebSource};
+use crate::send_queue::SendQueueStats;
+use crate::usage::{BudgetAction, TokenCounts};
 use std::time::Instant;
 
 /// Media events emitted by capture tasks
@@ -23,10 +26,27 @@ pub enum MediaEvent {
 }
 
 /// WebSocket outbound messages (Gemini protocol)
+///
+/// Payloads are kept raw; the Gemini client encodes and serializes them once
+/// when writing to the socket.
 #[derive(Clone, Debug)]
 pub enum WsOutbound {
-    /// JSON message to send to Gemini
-    Json(serde_json::Value),
+    /// Start of user activity (client-side VAD)
+    ActivityStart,
+    /// End of user activity, asks the model to respond
+    ActivityEnd,
+    /// Raw 16kHz mono PCM audio
+    Audio(Vec<u8>),
+    /// JPEG encoded video frame
+    Video(Vec<u8>),
+    /// Realtime text input
+    Text(String),
+    /// Mid-session update of the realtime input configuration
+    ConfigUpdate(RealtimeInputConfig),
+    /// Responses to a tool call
+    ToolResponse(ToolResponse),
+    /// Conversation content appended to the session history
+    ClientContent(ClientContent),
 }
 
 /// WebSocket inbound messages from Gemini
@@ -37,15 +57,68 @@ pub enum WsInbound {
         content: String,
         is_final: bool,
     },
+    /// Spoken response audio (16-bit PCM, 24kHz mono)
+    Audio(Vec<u8>),
+    /// Server transcription of the audio we sent
+    InputTranscription {
+        text: String,
+        is_final: bool,
+    },
+    /// Server transcription of a spoken response
+    OutputTranscription {
+        text: String,
+        is_final: bool,
+    },
+    /// Code the model is running with the built-in code execution tool
+    CodeExecution {
+        language: String,
+        code: String,
+    },
+    /// Outcome and output of the code the model ran
+    CodeExecutionResult {
+        outcome: String,
+        output: String,
+    },
+    /// Searches and web pages behind a grounded answer
+    Sources {
+        queries: Vec<String>,
+        sources: Vec<WebSource>,
+    },
     /// Generation completed
     GenerationComplete,
+    /// The response in progress was cut off by new user activity
+    Interrupted,
+    /// The model finished its turn
+    TurnComplete,
+    /// Token usage for the latest response and the session so far
+    Usage {
+        turn: TokenCounts,
+        session: TokenCounts,
+    },
+    /// The session went over its token budget and the action was applied
+    TokenBudgetExceeded(BudgetAction),
+    /// Old context was dropped to stay under the context window limit
+    ContextCompressed {
+        before_tokens: u64,
+        after_tokens: u64,
+    },
+    /// Outbound queue depth and media dropped so far
+    SendQueue(SendQueueStats),
     /// Tool call request
     ToolCall {
+        id: String,
         name: String,
         args: serde_json::Value,
     },
+    /// Answer from the stronger model a question was escalated to
+    Escalation {
+        content: String,
+        is_final: bool,
+    },
     /// Error from API
     Error(String),
+    /// The session failed and will not be retried
+    Fatal(String),
 }
 
 /// Turn boundary events from audio segmentation
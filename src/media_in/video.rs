@@ -3,19 +3,21 @@
 use crate::media_event::MediaEvent;
 use crate::screen::{ScreenCapturer, quick_hash};
 use anyhow::Result;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, watch};
 use tokio::time::{interval, Duration};
 use std::time::Instant;
 use tracing::{debug, error, info};
 use std::sync::atomic::{AtomicU64, Ordering};
 
-const FRAME_INTERVAL_MS: u64 = 500; // Capture a frame every .5 seconds
-
-pub fn spawn_video_capture(tx: broadcast::Sender<MediaEvent>) -> Result<()> {
-    info!("Starting video capture every {}ms", FRAME_INTERVAL_MS);
+/// Capture frames at the interval in `frame_interval`, which can change while running
+pub fn spawn_video_capture(
+    tx: broadcast::Sender<MediaEvent>,
+    frame_interval: watch::Receiver<Duration>,
+) -> Result<()> {
+    info!("Starting video capture every {:?}", *frame_interval.borrow());
     
     tokio::spawn(async move {
-        if let Err(e) = capture_loop(tx).await {
+        if let Err(e) = capture_loop(tx, frame_interval).await {
             error!("Video capture error: {}", e);
         }
     });
@@ -23,9 +25,13 @@ pub fn spawn_video_capture(tx: broadcast::Sender<MediaEvent>) -> Result<()> {
     Ok(())
 }
 
-async fn capture_loop(tx: broadcast::Sender<MediaEvent>) -> Result<()> {
-    let mut capturer = ScreenCapturer::new()?;
-    let mut ticker = interval(Duration::from_millis(FRAME_INTERVAL_MS));
+async fn capture_loop(
+    tx: broadcast::Sender<MediaEvent>,
+    mut frame_interval: watch::Receiver<Duration>,
+) -> Result<()> {
+    let period = *frame_interval.borrow_and_update();
+    let mut capturer = ScreenCapturer::with_options(period)?;
+    let mut ticker = interval(period);
     let mut last_hash = 0u64;
     let frame_counter = AtomicU64::new(0);
     
@@ -41,6 +47,13 @@ async fn capture_loop(tx: broadcast::Sender<MediaEvent>) -> Result<()> {
                 capture_and_send_frame(&mut capturer, &tx, &mut last_hash, &frame_counter, false);
             }
             
+            Ok(()) = frame_interval.changed() => {
+                let period = *frame_interval.borrow_and_update();
+                info!("Capturing a frame every {:?}", period);
+                capturer.set_capture_interval(period);
+                ticker = interval(period);
+            }
+            
             Ok(event) = rx.recv() => {
                 // Handle force capture requests
                 if let MediaEvent::ForceCaptureRequest { requester_id } = event {
@@ -0,0 +1,108 @@
+//! Playback of spoken model responses using PulseAudio
+
+use anyhow::{Context, Result};
+use libpulse_binding as pulse;
+use libpulse_simple_binding as psimple;
+use std::collections::VecDeque;
+use std::sync::mpsc::{Receiver, Sender};
+use tracing::{debug, error, info};
+
+/// Gemini speaks 16-bit mono PCM at 24kHz
+const SAMPLE_RATE: u32 = 24000;
+const CHANNELS: u8 = 1;
+const CHUNK_DURATION_MS: u32 = 20;
+const BYTES_PER_CHUNK: usize = (SAMPLE_RATE * CHUNK_DURATION_MS / 1000) as usize * 2;
+
+/// Target server-side buffer; bounds how much audio is still heard after a flush
+const TARGET_LATENCY_MS: u32 = 100;
+
+/// Commands for the playback thread
+#[derive(Debug)]
+pub enum PlaybackCommand {
+    /// Queue raw PCM for playback
+    Play(Vec<u8>),
+    /// Drop everything queued and cut off what is currently playing
+    Flush,
+}
+
+/// Start the playback thread and return the channel that feeds it.
+pub fn spawn_audio_playback() -> Sender<PlaybackCommand> {
+    info!("Starting audio playback at {}Hz", SAMPLE_RATE);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        if let Err(e) = playback_loop(rx) {
+            error!("Audio playback error: {}", e);
+        }
+    });
+
+    tx
+}
+
+fn playback_loop(rx: Receiver<PlaybackCommand>) -> Result<()> {
+    let spec = pulse::sample::Spec {
+        format: pulse::sample::Format::S16le,
+        channels: CHANNELS,
+        rate: SAMPLE_RATE,
+    };
+
+    // Keep the server buffer small so a flush takes effect quickly
+    let buffer_attr = pulse::def::BufferAttr {
+        maxlength: u32::MAX,
+        tlength: SAMPLE_RATE * TARGET_LATENCY_MS / 1000 * 2,
+        prebuf: u32::MAX,
+        minreq: u32::MAX,
+        fragsize: u32::MAX,
+    };
+
+    let playback = psimple::Simple::new(
+        None,                   // Use default server
+        "rholive_voice",       // Application name
+        pulse::stream::Direction::Playback,
+        None,                   // Use default device (speakers)
+        "assistant_voice",     // Stream description
+        &spec,
+        None,                   // Use default channel map
+        Some(&buffer_attr),
+    ).context("Failed to create PulseAudio playback connection")?;
+
+    info!("Audio playback connected successfully");
+
+    let mut queue: VecDeque<u8> = VecDeque::new();
+
+    loop {
+        // Block for work only when there is nothing left to play
+        let mut next = if queue.is_empty() {
+            match rx.recv() {
+                Ok(cmd) => Some(cmd),
+                Err(_) => break,
+            }
+        } else {
+            rx.try_recv().ok()
+        };
+
+        // Apply every command that is already waiting
+        while let Some(cmd) = next {
+            match cmd {
+                PlaybackCommand::Play(pcm) => queue.extend(pcm),
+                PlaybackCommand::Flush => {
+                    debug!("Flushing {} bytes of queued playback", queue.len());
+                    queue.clear();
+                    playback.flush().context("Failed to flush playback")?;
+                }
+            }
+            next = rx.try_recv().ok();
+        }
+
+        // Write one chunk at a time so flushes are noticed between chunks
+        if !queue.is_empty() {
+            let len = queue.len().min(BYTES_PER_CHUNK);
+            let chunk: Vec<u8> = queue.drain(..len).collect();
+            playback.write(&chunk).context("Failed to write playback audio")?;
+        }
+    }
+
+    playback.drain().context("Failed to drain playback")?;
+    info!("Audio playback stopped");
+    Ok(())
+}
@@ -0,0 +1,5 @@
+//! Media output module
+
+pub mod audio;
+
+pub use audio::{spawn_audio_playback, PlaybackCommand};
@@ -0,0 +1,341 @@
+//! Scriptable stand-in for the Gemini Live WebSocket endpoint
+//!
+//! The mock answers every `setup` with `setupComplete` (and an OpenAI Realtime
+//! `session.update` with `session.updated`) and then runs the replies
+//! scripted for whatever the client sent. Every message received on
+//! any connection is recorded so tests can assert on the wire traffic, along
+//! with the handshake headers of each connection.
+//!
+//! ```ignore
+//! let server = MockLiveServer::start(
+//!     Script::new().on(Trigger::ActivityEnd, Reply::Text("hello".into())),
+//! )
+//! .await;
+//! let config = GeminiClientConfig { url: server.url(), ..Default::default() };
+//! ```
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::http::HeaderMap;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, warn};
+
+/// Kind of client message a scripted reply reacts to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Initial session setup; mid-session config updates do not count
+    Setup,
+    ActivityStart,
+    ActivityEnd,
+    ToolResponse,
+    ClientContent,
+    /// OpenAI Realtime client event with this `type`
+    Event(&'static str),
+}
+
+impl Trigger {
+    fn matches(&self, msg: &Value) -> bool {
+        match self {
+            Trigger::Setup => msg["setup"].get("model").is_some(),
+            Trigger::ActivityStart => msg["realtimeInput"].get("activityStart").is_some(),
+            Trigger::ActivityEnd => msg["realtimeInput"].get("activityEnd").is_some(),
+            Trigger::ToolResponse => msg.get("toolResponse").is_some(),
+            Trigger::ClientContent => msg.get("clientContent").is_some(),
+            Trigger::Event(name) => msg["type"] == *name,
+        }
+    }
+}
+
+/// What the server does in response to a trigger
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// A complete model turn: text, then generationComplete and turnComplete
+    Text(String),
+    /// A `toolCall` with a single function call
+    ToolCall { id: String, name: String, args: Value },
+    /// A resumable `sessionResumptionUpdate` carrying this handle
+    ResumptionHandle(String),
+    /// A `goAway` announcing the time left
+    GoAway(Duration),
+    /// Close the TCP connection without a close frame
+    Drop,
+    /// Send a close frame with this code and reason
+    Close(u16, String),
+    /// Stop reading and writing but keep the connection open, like a half-open socket
+    Stall,
+    /// These messages, as given
+    Raw(Vec<Value>),
+    /// These messages instead of the usual acknowledgement, e.g. an error for a bad setup
+    Reject(Vec<Value>),
+}
+
+impl Reply {
+    fn messages(&self) -> Vec<Value> {
+        match self {
+            Reply::Text(text) => vec![
+                json!({ "serverContent": { "modelTurn": { "parts": [{ "text": text }] } } }),
+                json!({ "serverContent": { "generationComplete": true } }),
+                json!({ "serverContent": { "turnComplete": true } }),
+            ],
+            Reply::ToolCall { id, name, args } => vec![json!({
+                "toolCall": { "functionCalls": [{ "id": id, "name": name, "args": args }] }
+            })],
+            Reply::ResumptionHandle(handle) => vec![json!({
+                "sessionResumptionUpdate": { "newHandle": handle, "resumable": true }
+            })],
+            Reply::GoAway(time_left) => vec![json!({
+                "goAway": { "timeLeft": format!("{}s", time_left.as_secs_f64()) }
+            })],
+            Reply::Raw(messages) | Reply::Reject(messages) => messages.clone(),
+            Reply::Drop | Reply::Stall | Reply::Close(..) => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Rule {
+    trigger: Trigger,
+    reply: Reply,
+    /// Remaining number of times this rule fires, `None` for every time
+    remaining: Option<usize>,
+}
+
+/// Ordered list of scripted replies, shared by all connections
+#[derive(Debug, Default)]
+pub struct Script {
+    rules: Vec<Rule>,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reply every time the trigger is seen
+    pub fn on(mut self, trigger: Trigger, reply: Reply) -> Self {
+        self.rules.push(Rule {
+            trigger,
+            reply,
+            remaining: None,
+        });
+        self
+    }
+
+    /// Reply only the first time the trigger is seen, across all connections
+    pub fn once(mut self, trigger: Trigger, reply: Reply) -> Self {
+        self.rules.push(Rule {
+            trigger,
+            reply,
+            remaining: Some(1),
+        });
+        self
+    }
+
+    /// Take the replies due for a client message, in script order
+    fn replies_for(&mut self, msg: &Value) -> Vec<Reply> {
+        let mut replies = Vec::new();
+        for rule in &mut self.rules {
+            if !rule.trigger.matches(msg) || rule.remaining == Some(0) {
+                continue;
+            }
+            if let Some(remaining) = rule.remaining.as_mut() {
+                *remaining -= 1;
+            }
+            replies.push(rule.reply.clone());
+        }
+        replies
+    }
+}
+
+/// Local Live API server for offline tests
+pub struct MockLiveServer {
+    url: String,
+    /// Messages with the index of the connection they arrived on
+    received: Arc<Mutex<Vec<(usize, Value)>>>,
+    handshakes: Arc<Mutex<Vec<HeaderMap>>>,
+    connections: Arc<AtomicUsize>,
+    task: JoinHandle<()>,
+}
+
+impl MockLiveServer {
+    /// Bind to a free local port and start serving the script
+    pub async fn start(script: Script) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock Live server");
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let script = Arc::new(Mutex::new(script));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let handshakes = Arc::new(Mutex::new(Vec::new()));
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        let task = {
+            let received = received.clone();
+            let handshakes = handshakes.clone();
+            let connections = connections.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let index = connections.fetch_add(1, Ordering::SeqCst);
+                    tokio::spawn(serve_connection(
+                        index,
+                        stream,
+                        script.clone(),
+                        received.clone(),
+                        handshakes.clone(),
+                    ));
+                }
+            })
+        };
+
+        Self {
+            url,
+            received,
+            handshakes,
+            connections,
+            task,
+        }
+    }
+
+    /// URL to put in `GeminiClientConfig::url`
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// Every message received so far, across all connections
+    pub fn received(&self) -> Vec<Value> {
+        self.received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, msg)| msg.clone())
+            .collect()
+    }
+
+    /// Messages received on one connection, numbered from 0 in accept order
+    pub fn received_on(&self, connection: usize) -> Vec<Value> {
+        self.received
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(index, _)| *index == connection)
+            .map(|(_, msg)| msg.clone())
+            .collect()
+    }
+
+    /// Received messages that match a trigger
+    pub fn received_matching(&self, trigger: Trigger) -> Vec<Value> {
+        self.received()
+            .into_iter()
+            .filter(|msg| trigger.matches(msg))
+            .collect()
+    }
+
+    /// Handshake request headers, one entry per connection
+    pub fn handshake_headers(&self) -> Vec<HeaderMap> {
+        self.handshakes.lock().unwrap().clone()
+    }
+
+    /// Number of connections accepted so far
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for MockLiveServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// The handshake callback's error type is fixed by tungstenite
+#[allow(clippy::result_large_err)]
+async fn serve_connection(
+    index: usize,
+    stream: TcpStream,
+    script: Arc<Mutex<Script>>,
+    received: Arc<Mutex<Vec<(usize, Value)>>>,
+    handshakes: Arc<Mutex<Vec<HeaderMap>>>,
+) {
+    let record_headers = |request: &Request, response: Response| {
+        handshakes.lock().unwrap().push(request.headers().clone());
+        Ok(response)
+    };
+    let mut ws = match tokio_tungstenite::accept_hdr_async(stream, record_headers).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            warn!("Mock Live server handshake failed: {}", e);
+            return;
+        }
+    };
+
+    while let Some(Ok(frame)) = ws.next().await {
+        let text = match frame {
+            Message::Text(text) => text.to_string(),
+            Message::Binary(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        let msg: Value = match serde_json::from_str(&text) {
+            Ok(msg) => msg,
+            Err(e) => {
+                warn!("Mock Live server got invalid JSON: {}", e);
+                continue;
+            }
+        };
+        debug!("Mock Live server received: {}", msg);
+        received.lock().unwrap().push((index, msg.clone()));
+
+        // Mid-session setup messages only update config and are not acknowledged
+        let mut outgoing = Vec::new();
+        if Trigger::Setup.matches(&msg) {
+            outgoing.push(json!({ "setupComplete": {} }));
+        }
+        if Trigger::Event("session.update").matches(&msg) {
+            outgoing.push(json!({ "type": "session.updated", "session": msg["session"] }));
+        }
+
+        let replies = script.lock().unwrap().replies_for(&msg);
+        for reply in replies {
+            if let Reply::Drop | Reply::Stall = reply {
+                // Send what is already due, then vanish without a close frame
+                for msg in outgoing {
+                    let _ = ws.send(Message::Text(msg.to_string().into())).await;
+                }
+                if let Reply::Stall = reply {
+                    // Holding the socket without reading leaves pings unanswered
+                    std::future::pending::<()>().await;
+                }
+                return;
+            }
+            if let Reply::Close(code, reason) = reply {
+                // Rejects the message, so nothing else is sent
+                let frame = CloseFrame {
+                    code: CloseCode::from(code),
+                    reason: reason.into(),
+                };
+                let _ = ws.close(Some(frame)).await;
+                return;
+            }
+            if let Reply::Reject(_) = reply {
+                outgoing.clear();
+            }
+            outgoing.extend(reply.messages());
+        }
+
+        for msg in outgoing {
+            if ws.send(Message::Text(msg.to_string().into())).await.is_err() {
+                return;
+            }
+        }
+    }
+}
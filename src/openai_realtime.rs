@@ -0,0 +1,1076 @@
+//! OpenAI Realtime API client
+//!
+//! Implements [`LiveModelBackend`] over the Realtime WebSocket protocol. Turns
+//! are marked by the client just like with Gemini: server VAD is turned off,
+//! audio is appended to the input buffer during a turn and committed when it
+//! ends, followed by `response.create`.
+//!
+//! The Realtime API takes 24kHz audio, so captured audio is resampled. Screen
+//! frames cannot be streamed; the newest one is added to the conversation when
+//! the turn ends. Sessions cannot be resumed, a reconnect starts a new
+//! conversation seeded with `initial_context`; a new [`SessionSetup`] is
+//! applied to the live session with another `session.update` instead.
+
+use crate::auth::{redact_url, Credential};
+use crate::gemini::{
+    ActivityHandling, ApiResponse, ClientContent, Content, ErrorDetail, FunctionCall,
+    FunctionDeclaration, GeminiError, ResponseModality, Result, Retry, ServerError,
+    ServerErrorKind, ToolCall, ToolResponse, Transcript, UsageMetadata, MAX_RECONNECT_DELAY,
+};
+use crate::gemini_client::ConnectionEvent;
+use crate::live_backend::{LiveModelBackend, SessionSetup};
+
+use base64::engine::general_purpose;
+use base64::Engine;
+use futures_util::future::BoxFuture;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, error, info, warn};
+
+/// Realtime API endpoint; the model goes in the query string
+pub const REALTIME_API_URL: &str = "wss://api.openai.com/v1/realtime";
+
+/// Sample rate of captured audio
+const CAPTURE_SAMPLE_RATE: u32 = 16_000;
+
+/// Sample rate the Realtime API expects for `pcm16` input
+const INPUT_SAMPLE_RATE: u32 = 24_000;
+
+/// How long the server may take to acknowledge `session.update`
+const SETUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+type WsStream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+
+/// Configuration for the OpenAI Realtime client
+#[derive(Debug, Clone)]
+pub struct OpenAiRealtimeConfig {
+    /// Endpoint override; empty means [`REALTIME_API_URL`]
+    pub url: String,
+    pub model: String,
+    pub response_modality: ResponseModality,
+    pub instructions: Option<String>,
+    /// `None` leaves the server's default
+    pub temperature: Option<f32>,
+    /// Realtime voice for audio responses, e.g. `alloy` or `verse`
+    pub voice: Option<String>,
+    pub tools: Vec<FunctionDeclaration>,
+    /// Model transcribing the audio we send, `None` to disable
+    pub input_transcription_model: Option<String>,
+    /// Turns added to the conversation of every new session
+    pub initial_context: Vec<Content>,
+    pub reconnect_attempts: usize,
+    pub reconnect_delay: Duration,
+}
+
+impl Default for OpenAiRealtimeConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            model: "gpt-4o-realtime-preview".to_string(),
+            response_modality: ResponseModality::Text,
+            instructions: None,
+            temperature: None,
+            voice: None,
+            tools: Vec::new(),
+            input_transcription_model: Some("whisper-1".to_string()),
+            initial_context: Vec::new(),
+            reconnect_attempts: 3,
+            reconnect_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl OpenAiRealtimeConfig {
+    /// Take over the settings of `setup`; media resolution and the Gemini
+    /// built-in tools have no Realtime equivalent.
+    pub fn apply_setup(&mut self, setup: SessionSetup) {
+        if setup.media_resolution.is_some() || setup.code_execution || setup.google_search {
+            debug!("Ignoring media resolution and built-in tools for OpenAI Realtime");
+        }
+        self.instructions = Some(setup.instructions);
+        self.temperature = setup.temperature;
+        self.tools = setup.tools;
+    }
+}
+
+/// Client for a single OpenAI Realtime session at a time
+pub struct OpenAiRealtimeClient {
+    config: OpenAiRealtimeConfig,
+    api_key: String,
+    writer: Option<WsSink>,
+
+    // Outlives individual connections so subscribers keep receiving across reconnects
+    response_tx: mpsc::Sender<Result<ApiResponse>>,
+    response_rx: mpsc::Receiver<Result<ApiResponse>>,
+    events_rx: mpsc::UnboundedReceiver<ConnectionEvent>,
+    rx_task: Option<JoinHandle<()>>,
+
+    // Between `response.created` and `response.done`
+    responding: Arc<AtomicBool>,
+    interrupt_on_activity: bool,
+    // Audio appended since the last commit
+    audio_buffered: bool,
+    // Newest screen frame not yet added to the conversation
+    latest_frame: Option<Vec<u8>>,
+    resampler: Resampler,
+}
+
+impl OpenAiRealtimeClient {
+    pub fn new(api_key: impl Into<String>, config: OpenAiRealtimeConfig) -> Self {
+        let (response_tx, response_rx) = mpsc::channel(100);
+        let (_, events_rx) = mpsc::unbounded_channel();
+
+        Self {
+            config,
+            api_key: api_key.into(),
+            writer: None,
+            response_tx,
+            response_rx,
+            events_rx,
+            rx_task: None,
+            responding: Arc::new(AtomicBool::new(false)),
+            // Like the Gemini setup, new activity does not interrupt until asked to
+            interrupt_on_activity: false,
+            audio_buffered: false,
+            latest_frame: None,
+            resampler: Resampler::new(CAPTURE_SAMPLE_RATE, INPUT_SAMPLE_RATE),
+        }
+    }
+
+    /// Open a new session, replacing the current one.
+    pub async fn connect_and_setup(&mut self) -> Result<()> {
+        self.close().await;
+
+        let base = if self.config.url.is_empty() {
+            REALTIME_API_URL
+        } else {
+            &self.config.url
+        };
+        let url = format!("{}?model={}", base, self.config.model);
+        info!("Connecting to OpenAI Realtime API at {}", redact_url(&url));
+
+        let mut request = url.into_client_request()?;
+        Credential::Bearer(self.api_key.clone())
+            .apply(&mut request)
+            .map_err(|e| GeminiError::Auth(format!("{:#}", e)))?;
+        request
+            .headers_mut()
+            .insert("openai-beta", HeaderValue::from_static("realtime=v1"));
+
+        let (ws, _) = connect_async(request)
+            .await
+            .map_err(GeminiError::from_handshake)?;
+        let (writer, stream) = ws.split();
+        self.writer = Some(writer);
+
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        self.events_rx = events_rx;
+        self.responding.store(false, Ordering::SeqCst);
+        self.rx_task = Some(tokio::spawn(read_events(
+            stream,
+            self.response_tx.clone(),
+            events_tx,
+            self.responding.clone(),
+        )));
+
+        self.write(self.session_update()).await?;
+        let acknowledged = tokio::time::timeout(SETUP_TIMEOUT, async {
+            loop {
+                match self.events_rx.recv().await {
+                    Some(ConnectionEvent::SetupComplete) => return Ok(()),
+                    Some(ConnectionEvent::Failed(error)) => return Err(error.into()),
+                    Some(ConnectionEvent::Closed) | None => return Err(GeminiError::ConnectionClosed),
+                    Some(_) => continue,
+                }
+            }
+        })
+        .await
+        .map_err(|_| GeminiError::Timeout)?;
+        acknowledged?;
+        info!("OpenAI Realtime session set up");
+
+        self.audio_buffered = false;
+        self.resampler = Resampler::new(CAPTURE_SAMPLE_RATE, INPUT_SAMPLE_RATE);
+        for turn in self.config.initial_context.clone() {
+            if let Some(item) = message_item(&turn) {
+                self.write(item).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Close the socket without notifying subscribers.
+    async fn close(&mut self) {
+        if let Some(task) = self.rx_task.take() {
+            task.abort();
+        }
+        if let Some(mut writer) = self.writer.take() {
+            if tokio::time::timeout(Duration::from_secs(1), writer.close())
+                .await
+                .is_err()
+            {
+                debug!("Timed out closing old WebSocket");
+            }
+        }
+    }
+
+    /// Open a new session after the old one dropped, with exponential backoff.
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let mut delay = self.config.reconnect_delay;
+        let mut last_error = GeminiError::ConnectionClosed;
+
+        for attempt in 1..=self.config.reconnect_attempts {
+            info!(
+                "Reconnecting to OpenAI Realtime (attempt {}/{})",
+                attempt, self.config.reconnect_attempts
+            );
+            match self.connect_and_setup().await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    warn!("Reconnect attempt {} failed: {}", attempt, e);
+                    // Sessions are never resumed, so fresh and resumed retries are the same
+                    match e.retry() {
+                        Retry::Fatal => {
+                            error!("Not retrying OpenAI Realtime reconnect: {}", e);
+                            return Err(e);
+                        }
+                        Retry::Backoff => delay = MAX_RECONNECT_DELAY,
+                        Retry::Resume | Retry::Fresh => {}
+                    }
+                    last_error = e;
+                    if attempt < self.config.reconnect_attempts {
+                        tokio::time::sleep(delay).await;
+                        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    }
+                }
+            }
+        }
+
+        error!("Giving up on OpenAI Realtime reconnect");
+        Err(last_error)
+    }
+
+    /// Wait for the socket to close. Returns immediately if there is none.
+    pub async fn connection_lost(&mut self) -> ConnectionEvent {
+        loop {
+            match self.events_rx.recv().await {
+                Some(ConnectionEvent::SetupComplete) => continue,
+                Some(event) => return event,
+                None => return ConnectionEvent::Closed,
+            }
+        }
+    }
+
+    /// The `session.update` describing this client's configuration
+    fn session_update(&self) -> Value {
+        let modalities = match self.config.response_modality {
+            ResponseModality::Text => json!(["text"]),
+            ResponseModality::Audio => json!(["audio", "text"]),
+        };
+        let tools: Vec<Value> = self
+            .config
+            .tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "type": "function",
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool
+                        .parameters
+                        .clone()
+                        .unwrap_or_else(|| json!({ "type": "object", "properties": {} })),
+                })
+            })
+            .collect();
+
+        let mut session = json!({
+            "modalities": modalities,
+            "input_audio_format": "pcm16",
+            "output_audio_format": "pcm16",
+            // Turns are delimited by activity_start/activity_end
+            "turn_detection": null,
+            "input_audio_transcription": self
+                .config
+                .input_transcription_model
+                .as_ref()
+                .map(|model| json!({ "model": model })),
+            "tools": tools,
+            "tool_choice": "auto",
+        });
+        if let Some(instructions) = &self.config.instructions {
+            session["instructions"] = json!(instructions);
+        }
+        if let Some(temperature) = self.config.temperature {
+            session["temperature"] = json!(temperature);
+        }
+        if let Some(voice) = &self.config.voice {
+            session["voice"] = json!(voice);
+        }
+        json!({ "type": "session.update", "session": session })
+    }
+
+    /// Write a client event to the socket.
+    async fn write(&mut self, event: Value) -> Result<()> {
+        let writer = self.writer.as_mut().ok_or(GeminiError::ConnectionClosed)?;
+        writer.send(Message::Text(event.to_string().into())).await?;
+        Ok(())
+    }
+
+    async fn start_turn(&mut self) -> Result<()> {
+        if self.interrupt_on_activity && self.responding.load(Ordering::SeqCst) {
+            self.write(json!({ "type": "response.cancel" })).await?;
+        }
+        self.audio_buffered = false;
+        self.write(json!({ "type": "input_audio_buffer.clear" })).await
+    }
+
+    /// Commit the turn and ask for a response, unless the turn carried no input.
+    async fn end_turn(&mut self) -> Result<()> {
+        let frame_attached = self.latest_frame.is_some();
+        if let Some(jpeg) = self.latest_frame.take() {
+            let url = format!("data:image/jpeg;base64,{}", general_purpose::STANDARD.encode(jpeg));
+            self.write(json!({
+                "type": "conversation.item.create",
+                "item": {
+                    "type": "message",
+                    "role": "user",
+                    "content": [{ "type": "input_image", "image_url": url }],
+                },
+            }))
+            .await?;
+        }
+        let audio_buffered = std::mem::take(&mut self.audio_buffered);
+        if audio_buffered {
+            self.write(json!({ "type": "input_audio_buffer.commit" })).await?;
+        }
+        if !audio_buffered && !frame_attached {
+            debug!("Empty turn, not asking for a response");
+            return Ok(());
+        }
+        self.write(json!({ "type": "response.create" })).await
+    }
+
+    async fn append_audio(&mut self, pcm: Vec<u8>) -> Result<()> {
+        let audio = general_purpose::STANDARD.encode(self.resampler.process(&pcm));
+        self.write(json!({ "type": "input_audio_buffer.append", "audio": audio }))
+            .await?;
+        self.audio_buffered = true;
+        Ok(())
+    }
+
+    async fn add_text(&mut self, text: String) -> Result<()> {
+        let turn = Content {
+            role: Some("user".to_string()),
+            parts: vec![crate::gemini::Part {
+                text: Some(text),
+                ..Default::default()
+            }],
+        };
+        self.add_context(ClientContent {
+            turns: vec![turn],
+            turn_complete: true,
+        })
+        .await
+    }
+
+    async fn add_context(&mut self, content: ClientContent) -> Result<()> {
+        for turn in &content.turns {
+            if let Some(item) = message_item(turn) {
+                self.write(item).await?;
+            }
+        }
+        if content.turn_complete {
+            self.write(json!({ "type": "response.create" })).await?;
+        }
+        Ok(())
+    }
+
+    async fn add_tool_results(&mut self, response: ToolResponse) -> Result<()> {
+        for result in response.function_responses {
+            self.write(json!({
+                "type": "conversation.item.create",
+                "item": {
+                    "type": "function_call_output",
+                    "call_id": result.id,
+                    "output": result.response.to_string(),
+                },
+            }))
+            .await?;
+        }
+        self.write(json!({ "type": "response.create" })).await
+    }
+}
+
+impl LiveModelBackend for OpenAiRealtimeClient {
+    fn name(&self) -> &'static str {
+        "OpenAI Realtime"
+    }
+
+    fn connect(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.connect_and_setup())
+    }
+
+    fn subscribe(&mut self) -> mpsc::Receiver<Result<ApiResponse>> {
+        let (_, new_rx) = mpsc::channel(1);
+        std::mem::replace(&mut self.response_rx, new_rx)
+    }
+
+    fn activity_start(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.start_turn())
+    }
+
+    fn activity_end(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.end_turn())
+    }
+
+    fn send_audio(&mut self, pcm: Vec<u8>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.append_audio(pcm))
+    }
+
+    fn send_image(&mut self, jpeg: Vec<u8>) -> BoxFuture<'_, Result<()>> {
+        self.latest_frame = Some(jpeg);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn send_text(&mut self, text: String) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.add_text(text))
+    }
+
+    fn send_tool_response(&mut self, response: ToolResponse) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.add_tool_results(response))
+    }
+
+    fn send_context(&mut self, content: ClientContent) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.add_context(content))
+    }
+
+    fn set_activity_handling(&mut self, mode: ActivityHandling) -> BoxFuture<'_, Result<()>> {
+        self.interrupt_on_activity = mode == ActivityHandling::StartOfActivityInterrupts;
+        Box::pin(async { Ok(()) })
+    }
+
+    fn connection_lost(&mut self) -> BoxFuture<'_, ConnectionEvent> {
+        Box::pin(OpenAiRealtimeClient::connection_lost(self))
+    }
+
+    fn reconnect(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(OpenAiRealtimeClient::reconnect(self))
+    }
+
+    // The session can be updated in place, keeping the conversation
+    fn apply_setup(&mut self, setup: SessionSetup) -> BoxFuture<'_, Result<()>> {
+        self.config.apply_setup(setup);
+        Box::pin(async move {
+            if self.writer.is_none() {
+                return Ok(());
+            }
+            self.write(self.session_update()).await
+        })
+    }
+}
+
+/// A conversation item for a text turn, `None` if the turn has no text
+fn message_item(turn: &Content) -> Option<Value> {
+    let text: String = turn.parts.iter().filter_map(|part| part.text.as_deref()).collect();
+    if text.is_empty() {
+        return None;
+    }
+    let (role, kind) = match turn.role.as_deref() {
+        Some("model") => ("assistant", "text"),
+        _ => ("user", "input_text"),
+    };
+    Some(json!({
+        "type": "conversation.item.create",
+        "item": {
+            "type": "message",
+            "role": role,
+            "content": [{ "type": kind, "text": text }],
+        },
+    }))
+}
+
+/// Server events this client acts on; GA names are accepted as aliases
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum ServerEvent {
+    #[serde(rename = "session.updated")]
+    SessionUpdated {},
+    #[serde(rename = "response.created")]
+    ResponseCreated {},
+    #[serde(rename = "response.text.delta", alias = "response.output_text.delta")]
+    TextDelta { delta: String },
+    #[serde(rename = "response.text.done", alias = "response.output_text.done")]
+    TextDone {},
+    #[serde(rename = "response.audio.delta", alias = "response.output_audio.delta")]
+    AudioDelta { delta: String },
+    #[serde(
+        rename = "response.audio_transcript.delta",
+        alias = "response.output_audio_transcript.delta"
+    )]
+    TranscriptDelta { delta: String },
+    #[serde(
+        rename = "response.audio_transcript.done",
+        alias = "response.output_audio_transcript.done"
+    )]
+    TranscriptDone {},
+    #[serde(rename = "conversation.item.input_audio_transcription.completed")]
+    InputTranscript { transcript: String },
+    #[serde(rename = "response.function_call_arguments.done")]
+    FunctionCall {
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+    #[serde(rename = "response.done")]
+    ResponseDone { response: ResponseSummary },
+    #[serde(rename = "error")]
+    Error { error: ErrorDetail },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseSummary {
+    /// `completed`, `cancelled`, `failed` or `incomplete`
+    status: String,
+    #[serde(default)]
+    usage: Option<RealtimeUsage>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RealtimeUsage {
+    total_tokens: u64,
+    input_tokens: u64,
+    output_tokens: u64,
+    input_token_details: InputTokenDetails,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct InputTokenDetails {
+    cached_tokens: u64,
+}
+
+impl From<RealtimeUsage> for UsageMetadata {
+    fn from(usage: RealtimeUsage) -> Self {
+        UsageMetadata {
+            prompt_token_count: usage.input_tokens,
+            cached_content_token_count: usage.input_token_details.cached_tokens,
+            response_token_count: usage.output_tokens,
+            total_token_count: usage.total_tokens,
+            ..Default::default()
+        }
+    }
+}
+
+/// Translate a server event into the responses it carries, in delivery order.
+fn event_responses(event: ServerEvent) -> Vec<Result<ApiResponse>> {
+    let response = match event {
+        ServerEvent::SessionUpdated {} => ApiResponse::SetupComplete,
+        ServerEvent::TextDelta { delta } => ApiResponse::TextResponse {
+            text: delta,
+            is_complete: false,
+        },
+        ServerEvent::TextDone {} => ApiResponse::TextResponse {
+            text: String::new(),
+            is_complete: true,
+        },
+        ServerEvent::AudioDelta { delta } => match general_purpose::STANDARD.decode(delta) {
+            Ok(data) => ApiResponse::AudioResponse {
+                data,
+                is_complete: false,
+            },
+            Err(e) => {
+                error!("Failed to decode Realtime audio: {}", e);
+                return Vec::new();
+            }
+        },
+        ServerEvent::TranscriptDelta { delta } => ApiResponse::OutputTranscription(Transcript {
+            text: delta,
+            is_final: false,
+        }),
+        ServerEvent::TranscriptDone {} => ApiResponse::OutputTranscription(Transcript {
+            text: String::new(),
+            is_final: true,
+        }),
+        ServerEvent::InputTranscript { transcript } => ApiResponse::InputTranscription(Transcript {
+            text: transcript,
+            is_final: true,
+        }),
+        ServerEvent::FunctionCall {
+            call_id,
+            name,
+            arguments,
+        } => {
+            let args = match serde_json::from_str(&arguments) {
+                Ok(args) => args,
+                Err(e) => {
+                    warn!("Function call {} has invalid arguments: {}", name, e);
+                    Value::Object(Default::default())
+                }
+            };
+            ApiResponse::ToolCall(ToolCall {
+                function_calls: vec![FunctionCall {
+                    id: call_id,
+                    name,
+                    args,
+                }],
+            })
+        }
+        ServerEvent::ResponseDone { response } => {
+            let mut responses = Vec::new();
+            if let Some(usage) = response.usage {
+                responses.push(Ok(ApiResponse::Usage(usage.into())));
+            }
+            match response.status.as_str() {
+                "cancelled" => responses.push(Ok(ApiResponse::Interrupted)),
+                "completed" => responses.push(Ok(ApiResponse::GenerationComplete)),
+                status => {
+                    warn!("Realtime response ended with status {}", status);
+                    responses.push(Ok(ApiResponse::GenerationComplete));
+                }
+            }
+            responses.push(Ok(ApiResponse::TurnComplete));
+            return responses;
+        }
+        ServerEvent::Error { mut error } => {
+            if error.kind().is_none() {
+                error.message = format!("OpenAI Realtime error: {}", error.message);
+            }
+            return vec![Err(error.into_error())];
+        }
+        ServerEvent::ResponseCreated {} | ServerEvent::Other => return Vec::new(),
+    };
+    vec![Ok(response)]
+}
+
+/// Forward server events until the socket closes.
+async fn read_events(
+    mut stream: WsStream,
+    response_tx: mpsc::Sender<Result<ApiResponse>>,
+    events_tx: mpsc::UnboundedSender<ConnectionEvent>,
+    responding: Arc<AtomicBool>,
+) {
+    // Error reported in the server's close frame
+    let mut failure = None;
+    // Whether the session.update was acknowledged
+    let mut set_up = false;
+    while let Some(frame) = stream.next().await {
+        let text = match frame {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(frame)) => {
+                info!("OpenAI Realtime closed the connection: {:?}", frame);
+                failure = frame.as_ref().and_then(|frame| {
+                    ServerError::from_close(u16::from(frame.code), &frame.reason)
+                });
+                if let Some(failure) = &failure {
+                    let _ = response_tx.send(Err(failure.clone().into())).await;
+                }
+                break;
+            }
+            Ok(_) => continue,
+            Err(e) => {
+                error!("OpenAI Realtime WebSocket error: {}", e);
+                let _ = response_tx.send(Err(e.into())).await;
+                break;
+            }
+        };
+
+        let event = match serde_json::from_str::<ServerEvent>(&text) {
+            Ok(event) => event,
+            Err(e) => {
+                warn!("Could not parse Realtime event: {}", e);
+                continue;
+            }
+        };
+        match &event {
+            ServerEvent::SessionUpdated {} => {
+                set_up = true;
+                let _ = events_tx.send(ConnectionEvent::SetupComplete);
+            }
+            ServerEvent::Error { error } if !set_up => {
+                // The session.update was rejected; connect_and_setup is waiting on this
+                let error = ServerError {
+                    kind: error.kind().unwrap_or(ServerErrorKind::InvalidArgument),
+                    message: error.message.clone(),
+                };
+                let _ = events_tx.send(ConnectionEvent::Failed(error));
+            }
+            ServerEvent::ResponseCreated {} => responding.store(true, Ordering::SeqCst),
+            ServerEvent::ResponseDone { .. } => responding.store(false, Ordering::SeqCst),
+            _ => {}
+        }
+        for response in event_responses(event) {
+            if response_tx.send(response).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    let _ = events_tx.send(match failure {
+        Some(failure) => ConnectionEvent::Failed(failure),
+        None => ConnectionEvent::Closed,
+    });
+    let _ = response_tx.send(Ok(ApiResponse::ConnectionClosed)).await;
+}
+
+/// Linear resampler for a continuous stream of 16-bit mono PCM chunks
+#[derive(Debug)]
+struct Resampler {
+    /// Input samples per output sample
+    step: f64,
+    /// Position of the next output sample, counted in input samples after `last`
+    position: f64,
+    /// Final sample of the previous chunk
+    last: i16,
+}
+
+impl Resampler {
+    fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self {
+            step: from_rate as f64 / to_rate as f64,
+            position: 1.0,
+            last: 0,
+        }
+    }
+
+    fn process(&mut self, pcm: &[u8]) -> Vec<u8> {
+        let input: Vec<i16> = pcm
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+        let last = self.last;
+        let sample = |index: usize| if index == 0 { last } else { input[index - 1] } as f64;
+
+        let mut output = Vec::with_capacity((input.len() as f64 / self.step) as usize * 2 + 2);
+        while self.position < input.len() as f64 {
+            let index = self.position as usize;
+            let fraction = self.position - index as f64;
+            let (a, b) = (sample(index), sample(index + 1));
+            output.extend_from_slice(&((a + (b - a) * fraction).round() as i16).to_le_bytes());
+            self.position += self.step;
+        }
+
+        if let Some(&last) = input.last() {
+            self.position -= input.len() as f64;
+            self.last = last;
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gemini::FunctionResponse;
+    use crate::mock_live::{MockLiveServer, Reply, Script, Trigger};
+
+    fn client_for(server: &MockLiveServer) -> OpenAiRealtimeClient {
+        OpenAiRealtimeClient::new(
+            "sk-test",
+            OpenAiRealtimeConfig {
+                url: format!("{}/v1/realtime", server.url()),
+                model: "test-model".to_string(),
+                instructions: Some("be brief".to_string()),
+                reconnect_delay: Duration::from_millis(10),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Next response, skipping setup acknowledgements
+    async fn next(rx: &mut mpsc::Receiver<Result<ApiResponse>>) -> ApiResponse {
+        loop {
+            let response = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .expect("timed out waiting for a response")
+                .expect("response channel closed")
+                .expect("client reported an error");
+            if !matches!(response, ApiResponse::SetupComplete) {
+                return response;
+            }
+        }
+    }
+
+    /// Wait until the server has received `count` messages matching the trigger
+    async fn wait_for(server: &MockLiveServer, trigger: Trigger, count: usize) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while server.received_matching(trigger).len() < count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("timed out waiting for the server to receive a message");
+    }
+
+    fn types(received: &[Value]) -> Vec<&str> {
+        received.iter().map(|msg| msg["type"].as_str().unwrap_or("")).collect()
+    }
+
+    #[test]
+    fn test_resampler_continues_across_chunks() {
+        let ramp = |values: &[i16]| -> Vec<u8> { values.iter().flat_map(|v| v.to_le_bytes()).collect() };
+        let mut resampler = Resampler::new(16_000, 24_000);
+
+        let mut output = resampler.process(&ramp(&[0, 30, 60, 90]));
+        output.extend(resampler.process(&ramp(&[120, 150])));
+
+        let samples: Vec<i16> = output
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+        assert_eq!(samples, [0, 20, 40, 60, 80, 100, 120, 140]);
+    }
+
+    #[tokio::test]
+    async fn test_turn_is_committed_and_answered() {
+        let server = MockLiveServer::start(Script::new().on(
+            Trigger::Event("response.create"),
+            Reply::Raw(vec![
+                json!({ "type": "response.created", "response": {} }),
+                json!({ "type": "response.text.delta", "delta": "hel" }),
+                json!({ "type": "response.output_text.delta", "delta": "lo" }),
+                json!({ "type": "response.text.done", "text": "hello" }),
+                json!({
+                    "type": "response.done",
+                    "response": {
+                        "status": "completed",
+                        "usage": { "total_tokens": 30, "input_tokens": 20, "output_tokens": 10 },
+                    },
+                }),
+            ]),
+        ))
+        .await;
+        let mut client: Box<dyn LiveModelBackend> = Box::new(client_for(&server));
+        client.connect().await.unwrap();
+        let mut rx = client.subscribe();
+
+        client.send(crate::media_event::WsOutbound::ActivityStart).await.unwrap();
+        client.send_audio(vec![0; 640]).await.unwrap();
+        client.send_image(vec![1, 2, 3]).await.unwrap();
+        client.activity_end().await.unwrap();
+
+        assert!(matches!(next(&mut rx).await, ApiResponse::TextResponse { text, is_complete: false } if text == "hel"));
+        assert!(matches!(next(&mut rx).await, ApiResponse::TextResponse { text, is_complete: false } if text == "lo"));
+        assert!(matches!(next(&mut rx).await, ApiResponse::TextResponse { is_complete: true, .. }));
+        assert!(matches!(next(&mut rx).await, ApiResponse::Usage(usage) if usage.prompt_token_count == 20 && usage.total_token_count == 30));
+        assert!(matches!(next(&mut rx).await, ApiResponse::GenerationComplete));
+        assert!(matches!(next(&mut rx).await, ApiResponse::TurnComplete));
+
+        let headers = &server.handshake_headers()[0];
+        assert_eq!(headers["authorization"], "Bearer sk-test");
+        assert_eq!(headers["openai-beta"], "realtime=v1");
+
+        let received = server.received();
+        assert_eq!(
+            types(&received),
+            [
+                "session.update",
+                "input_audio_buffer.clear",
+                "input_audio_buffer.append",
+                "conversation.item.create",
+                "input_audio_buffer.commit",
+                "response.create",
+            ]
+        );
+        assert_eq!(received[0]["session"]["instructions"], "be brief");
+        assert!(received[0]["session"]["turn_detection"].is_null());
+        // 320 samples at 16kHz become ~480 at 24kHz
+        let audio = general_purpose::STANDARD
+            .decode(received[2]["audio"].as_str().unwrap())
+            .unwrap();
+        assert_eq!(audio.len() / 2, 479);
+        assert_eq!(
+            received[3]["item"]["content"][0]["image_url"],
+            "data:image/jpeg;base64,AQID"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_setup_updates_live_session() {
+        let server = MockLiveServer::start(Script::new()).await;
+        let mut client = client_for(&server);
+        client.connect_and_setup().await.unwrap();
+
+        client
+            .apply_setup(SessionSetup {
+                instructions: "take meeting notes".to_string(),
+                temperature: Some(0.6),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        wait_for(&server, Trigger::Event("session.update"), 2).await;
+
+        assert_eq!(server.connections(), 1);
+        let session = &server.received()[1]["session"];
+        assert_eq!(session["instructions"], "take meeting notes");
+        assert_eq!(session["temperature"].as_f64().unwrap() as f32, 0.6);
+        assert!(session["turn_detection"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_round_trip() {
+        let server = MockLiveServer::start(Script::new().once(
+            Trigger::Event("response.create"),
+            Reply::Raw(vec![
+                json!({
+                    "type": "response.function_call_arguments.done",
+                    "call_id": "call_1",
+                    "name": "current_time",
+                    "arguments": "{\"zone\":\"UTC\"}",
+                }),
+                json!({ "type": "response.done", "response": { "status": "completed" } }),
+            ]),
+        ))
+        .await;
+        let mut client = client_for(&server);
+        client.connect_and_setup().await.unwrap();
+        let mut rx = LiveModelBackend::subscribe(&mut client);
+
+        client.send_text("what time is it".to_string()).await.unwrap();
+        match next(&mut rx).await {
+            ApiResponse::ToolCall(call) => {
+                assert_eq!(call.function_calls[0].id, "call_1");
+                assert_eq!(call.function_calls[0].args, json!({ "zone": "UTC" }));
+            }
+            other => panic!("expected a tool call, got {:?}", other),
+        }
+
+        client
+            .send_tool_response(ToolResponse {
+                function_responses: vec![FunctionResponse {
+                    id: "call_1".to_string(),
+                    name: "current_time".to_string(),
+                    response: json!({ "time": "12:00" }),
+                    scheduling: None,
+                }],
+            })
+            .await
+            .unwrap();
+        wait_for(&server, Trigger::Event("response.create"), 2).await;
+
+        let received = server.received();
+        assert_eq!(received[1]["item"]["content"][0]["text"], "what time is it");
+        assert_eq!(received[3]["item"]["type"], "function_call_output");
+        assert_eq!(received[3]["item"]["call_id"], "call_1");
+        assert_eq!(received[3]["item"]["output"], "{\"time\":\"12:00\"}");
+    }
+
+    #[tokio::test]
+    async fn test_rejected_session_update_fails_fast() {
+        let server = MockLiveServer::start(Script::new().on(
+            Trigger::Event("session.update"),
+            Reply::Reject(vec![json!({
+                "type": "error",
+                "error": {
+                    "type": "invalid_request_error",
+                    "code": "model_not_found",
+                    "message": "The model `test-model` does not exist.",
+                },
+            })]),
+        ))
+        .await;
+        let mut client = client_for(&server);
+
+        let started = std::time::Instant::now();
+        let error = client.connect_and_setup().await.unwrap_err();
+        assert!(started.elapsed() < SETUP_TIMEOUT / 2, "waited {:?}", started.elapsed());
+        assert!(matches!(
+            &error,
+            GeminiError::Server(ServerError { kind: ServerErrorKind::ModelNotFound, .. })
+        ));
+        assert_eq!(error.retry(), Retry::Fatal);
+
+        // A fatal error is not retried
+        assert!(client.reconnect().await.is_err());
+        assert_eq!(server.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_new_activity_cancels_response_only_when_enabled() {
+        let server = MockLiveServer::start(Script::new().on(
+            Trigger::Event("response.create"),
+            Reply::Raw(vec![json!({ "type": "response.created", "response": {} })]),
+        ))
+        .await;
+        let mut client = client_for(&server);
+        client.connect_and_setup().await.unwrap();
+        client
+            .set_activity_handling(ActivityHandling::StartOfActivityInterrupts)
+            .await
+            .unwrap();
+
+        client.append_audio(vec![0; 640]).await.unwrap();
+        client.end_turn().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !client.responding.load(Ordering::SeqCst) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        client.start_turn().await.unwrap();
+        client
+            .set_activity_handling(ActivityHandling::NoInterruption)
+            .await
+            .unwrap();
+        client.start_turn().await.unwrap();
+        wait_for(&server, Trigger::Event("input_audio_buffer.clear"), 2).await;
+
+        assert_eq!(
+            types(&server.received()),
+            [
+                "session.update",
+                "input_audio_buffer.append",
+                "input_audio_buffer.commit",
+                "response.create",
+                "response.cancel",
+                "input_audio_buffer.clear",
+                "input_audio_buffer.clear",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty_turn_asks_for_no_response() {
+        let server = MockLiveServer::start(Script::new()).await;
+        let mut client = client_for(&server);
+        client.connect_and_setup().await.unwrap();
+
+        client.start_turn().await.unwrap();
+        client.end_turn().await.unwrap();
+        // A turn with only a frame is still answered
+        client.start_turn().await.unwrap();
+        client.latest_frame = Some(vec![1, 2, 3]);
+        client.end_turn().await.unwrap();
+        wait_for(&server, Trigger::Event("response.create"), 1).await;
+
+        assert_eq!(
+            types(&server.received()),
+            [
+                "session.update",
+                "input_audio_buffer.clear",
+                "input_audio_buffer.clear",
+                "conversation.item.create",
+                "response.create",
+            ]
+        );
+    }
+}
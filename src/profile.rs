@@ -0,0 +1,272 @@
+//! Prompt profiles
+//!
+//! A profile bundles how the assistant behaves: its system instruction,
+//! temperature, media resolution, how often the screen is captured and which
+//! tools it may use. Profiles are JSON files named `<name>.json` in the
+//! profile directory:
+//!
+//! ```json
+//! {
+//!   "system_instruction": "Take notes during the meeting...",
+//!   "temperature": 0.5,
+//!   "media_resolution": "low",
+//!   "frame_interval_ms": 2000,
+//!   "tools": ["get_current_time", "google_search"]
+//! }
+//! ```
+//!
+//! Only the instruction is required. Settings left out use the provider's
+//! default; without `tools` every registered function tool is enabled, and
+//! `code_execution` and `google_search` turn on Gemini's built-ins. Naming a
+//! tool that does not exist is an error. The `default` profile is built in
+//! unless a file replaces it.
+
+use crate::escalation;
+use crate::gemini::MediaResolution;
+use crate::live_backend::SessionSetup;
+use crate::tools::ToolRegistry;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+
+/// Profile used when none is selected
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Tool names in a profile that enable Gemini's built-in tools
+pub const CODE_EXECUTION: &str = "code_execution";
+pub const GOOGLE_SEARCH: &str = "google_search";
+
+/// Tools a profile may name besides the registered functions; `escalate` is
+/// only registered when an escalation model is configured
+const BUILT_IN_TOOLS: [&str; 3] = [CODE_EXECUTION, GOOGLE_SEARCH, escalation::TOOL_NAME];
+
+/// Screen capture interval unless a profile sets one
+const DEFAULT_FRAME_INTERVAL_MS: u64 = 500;
+
+/// Instruction of the built-in default profile
+const DEFAULT_INSTRUCTION: &str = "you are, rholive, a silent helper meant to assist the user in whatever task they choose. if you see a leetcode problem on the screen, solve it without waiting for them to say anything. if someone they are on call with asks you a question, answer it. you are effectively their second mind, they should not have to do any thinking, they should not have to ask you for anything. you are their brain, they should not have to think, respond to whatever is on screen or whatever someone says like the user would.
+          you have access to the users screen, microphone and system audio.
+            when there is no change or nothing to work, do, or comment on, respond only with '<nothing>' (without quotes). if you don't understand what is going on, respond only with '<nothing>'. please be quiet until the user asks you something or u know what to do (i.e. respond with '<nothing>').
+            keep in mind, the user can only really see a few lines, so when you respond start with first thing wait and continually do more.
+            ";
+
+/// Named assistant behaviour
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// File name without `.json`
+    #[serde(skip)]
+    pub name: String,
+    pub system_instruction: String,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub media_resolution: Option<MediaResolution>,
+    /// Minimum time between screen frames
+    #[serde(default = "default_frame_interval_ms")]
+    pub frame_interval_ms: u64,
+    /// Enabled tools, `None` for every registered function tool
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+}
+
+fn default_frame_interval_ms() -> u64 {
+    DEFAULT_FRAME_INTERVAL_MS
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_PROFILE.to_string(),
+            system_instruction: DEFAULT_INSTRUCTION.to_string(),
+            temperature: Some(0.7),
+            media_resolution: Some(MediaResolution::Medium),
+            frame_interval_ms: DEFAULT_FRAME_INTERVAL_MS,
+            tools: None,
+        }
+    }
+}
+
+impl Profile {
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_millis(self.frame_interval_ms)
+    }
+
+    /// Whether the profile lets the model use the named tool
+    pub fn enables(&self, tool: &str) -> bool {
+        match &self.tools {
+            Some(tools) => tools.iter().any(|t| t == tool),
+            None => tool != CODE_EXECUTION && tool != GOOGLE_SEARCH,
+        }
+    }
+
+    /// Session settings for this profile, declaring its tools from `registry`
+    pub fn session_setup(&self, registry: &ToolRegistry) -> SessionSetup {
+        SessionSetup {
+            instructions: self.system_instruction.clone(),
+            temperature: self.temperature,
+            media_resolution: self.media_resolution,
+            tools: registry
+                .declaration_where(|name| self.enables(name))
+                .map(|declaration| declaration.function_declarations)
+                .unwrap_or_default(),
+            code_execution: self.enables(CODE_EXECUTION),
+            google_search: self.enables(GOOGLE_SEARCH),
+        }
+    }
+}
+
+/// Read the profile `name` from `dir`, falling back to the built-in default.
+///
+/// The tools it names must be built in or registered in `registry`.
+pub fn load(dir: &Path, name: &str, registry: &ToolRegistry) -> Result<Profile> {
+    let path = dir.join(format!("{}.json", name));
+    if !path.exists() && name == DEFAULT_PROFILE {
+        return Ok(Profile::default());
+    }
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read profile {}", path.display()))?;
+    parse(name, &text, registry).with_context(|| format!("Invalid profile {}", path.display()))
+}
+
+fn parse(name: &str, text: &str, registry: &ToolRegistry) -> Result<Profile> {
+    let mut profile: Profile = serde_json::from_str(text)?;
+    if profile.frame_interval_ms == 0 {
+        bail!("frame_interval_ms must be positive");
+    }
+    if let Some(temperature) = profile.temperature {
+        if !(0.0..=2.0).contains(&temperature) {
+            bail!("temperature {} is outside 0 to 2", temperature);
+        }
+    }
+    for tool in profile.tools.iter().flatten() {
+        if !BUILT_IN_TOOLS.contains(&tool.as_str()) && !registry.names().any(|name| name == tool) {
+            let mut valid: Vec<&str> = registry.names().chain(BUILT_IN_TOOLS).collect();
+            valid.sort();
+            valid.dedup();
+            bail!("unknown tool {:?}, expected one of: {}", tool, valid.join(", "));
+        }
+    }
+    profile.name = name.to_string();
+    Ok(profile)
+}
+
+/// Names of the profiles in `dir`, sorted, always including the default.
+pub fn list(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            Some(path.file_stem()?.to_str()?.to_string())
+        })
+        .collect();
+    if !names.iter().any(|name| name == DEFAULT_PROFILE) {
+        names.push(DEFAULT_PROFILE.to_string());
+    }
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::CurrentTime;
+
+    /// The function tools main registers regardless of configuration
+    fn registry() -> ToolRegistry {
+        let mut registry = ToolRegistry::new();
+        registry.register(CurrentTime);
+        registry
+    }
+
+    #[test]
+    fn test_parse_profile() {
+        let profile = parse(
+            "meeting",
+            r#"{
+                "system_instruction": "take notes",
+                "temperature": 0.4,
+                "media_resolution": "low",
+                "frame_interval_ms": 2000,
+                "tools": ["get_current_time", "google_search"]
+            }"#,
+            &registry(),
+        )
+        .unwrap();
+        assert_eq!(profile.name, "meeting");
+        assert_eq!(profile.media_resolution, Some(MediaResolution::Low));
+        assert_eq!(profile.frame_interval(), Duration::from_secs(2));
+        assert!(profile.enables("get_current_time"));
+        assert!(profile.enables(GOOGLE_SEARCH));
+        assert!(!profile.enables("escalate"));
+        assert!(!profile.enables(CODE_EXECUTION));
+
+        let minimal = parse("reading", r#"{"system_instruction": "explain"}"#, &registry()).unwrap();
+        assert_eq!(minimal.temperature, None);
+        assert_eq!(minimal.frame_interval_ms, DEFAULT_FRAME_INTERVAL_MS);
+        assert!(minimal.enables("get_current_time"));
+        assert!(!minimal.enables(GOOGLE_SEARCH));
+
+        let invalid = |text| parse("x", text, &registry()).is_err();
+        assert!(invalid(r#"{"system_instruction": "x", "temprature": 1}"#));
+        assert!(invalid(r#"{"system_instruction": "x", "temperature": 3}"#));
+        assert!(invalid(r#"{"system_instruction": "x", "frame_interval_ms": 0}"#));
+
+        let error = parse(
+            "x",
+            r#"{"system_instruction": "x", "tools": ["current_time"]}"#,
+            &registry(),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("\"current_time\""), "{}", error);
+        assert!(
+            error.contains("code_execution, escalate, get_current_time, google_search"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_load_and_list() {
+        let dir = std::env::temp_dir().join(format!("rholive-profiles-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("reading.json"), r#"{"system_instruction": "explain"}"#).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a profile").unwrap();
+
+        let names = list(&dir);
+        let reading = load(&dir, "reading", &registry()).unwrap();
+        let default = load(&dir, DEFAULT_PROFILE, &registry()).unwrap();
+        let missing = load(&dir, "missing", &registry());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(names, ["default", "reading"]);
+        assert_eq!(reading.system_instruction, "explain");
+        assert_eq!(default, Profile::default());
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn test_shipped_profiles_load() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("profiles");
+        let registry = registry();
+        let names = list(&dir);
+        assert!(names.len() > 1, "no profiles in {}", dir.display());
+        for name in names {
+            let profile = load(&dir, &name, &registry).unwrap_or_else(|e| panic!("{:#}", e));
+            // Every function tool the profile names is declared
+            let declared = profile.session_setup(&registry).tools;
+            for tool in profile.tools.iter().flatten() {
+                if registry.names().any(|registered| registered == tool) {
+                    let declared = declared.iter().any(|d| &d.name == tool);
+                    assert!(declared, "{} not declared in {}", tool, name);
+                }
+            }
+        }
+    }
+}
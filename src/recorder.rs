@@ -1,7 +1,6 @@
 //! Turn recorder for testing - saves frames and audio to filesystem
 
 use crate::media_event::{Outgoing, WsOutbound};
-use base64::Engine;
 use chrono::Local;
 use std::fs::{self, File};
 use std::io::{Write, BufWriter};
@@ -125,72 +124,62 @@ impl TurnRecorder {
         }
         
         match msg {
-            WsOutbound::Json(json) => {
-                // Handle activityStart for video-only turns
-                if json.get("activityStart").is_some() && self.cur_dir.is_none() {
-                    // Create a directory for this turn
-                    static mut VIDEO_TURN_COUNTER: u64 = 1000; // Start at 1000 to distinguish from audio turns
-                    let turn_id = unsafe {
-                        let id = VIDEO_TURN_COUNTER;
-                        VIDEO_TURN_COUNTER += 1;
-                        id
-                    };
-                    
-                    let dir = self.base.join(format!(
-                        "turn_v{:03}_{}", 
-                        turn_id,
-                        Local::now().format("%H%M%S%.3f")
-                    ));
-                    
-                    if let Err(e) = fs::create_dir_all(&dir) {
-                        error!("Failed to create video turn directory: {}", e);
-                        return;
-                    }
-                    
-                    debug!("Starting recording for video turn {} in {:?}", turn_id, dir);
-                    self.cur_dir = Some(dir);
+            // Handle activityStart for video-only turns
+            WsOutbound::ActivityStart if self.cur_dir.is_none() => {
+                // Create a directory for this turn
+                static mut VIDEO_TURN_COUNTER: u64 = 1000; // Start at 1000 to distinguish from audio turns
+                let turn_id = unsafe {
+                    let id = VIDEO_TURN_COUNTER;
+                    VIDEO_TURN_COUNTER += 1;
+                    id
+                };
+                
+                let dir = self.base.join(format!(
+                    "turn_v{:03}_{}", 
+                    turn_id,
+                    Local::now().format("%H%M%S%.3f")
+                ));
+                
+                if let Err(e) = fs::create_dir_all(&dir) {
+                    error!("Failed to create video turn directory: {}", e);
+                    return;
                 }
                 
-                // Check if this is a video frame
-                if let Some(video) = json.get("video") {
-                    if let Some(data_b64) = video.get("data").and_then(|d| d.as_str()) {
-                        if let Some(dir) = &self.cur_dir {
-                            // Decode base64 JPEG data
-                            match base64::engine::general_purpose::STANDARD.decode(data_b64) {
-                                Ok(bytes) => {
-                                    let ts = Local::now().format("%H%M%S%.3f");
-                                    let path = dir.join(format!("frame_{}.jpg", ts));
-                                    
-                                    match File::create(&path) {
-                                        Ok(mut file) => {
-                                            if let Err(e) = file.write_all(&bytes) {
-                                                error!("Failed to write frame: {}", e);
-                                            } else {
-                                                debug!("Saved frame to {:?}", path);
-                                            }
-                                        }
-                                        Err(e) => {
-                                            error!("Failed to create frame file: {}", e);
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    error!("Failed to decode frame base64: {}", e);
-                                }
+                debug!("Starting recording for video turn {} in {:?}", turn_id, dir);
+                self.cur_dir = Some(dir);
+            }
+            
+            // Save video frames into the open turn directory
+            WsOutbound::Video(jpeg) => {
+                if let Some(dir) = &self.cur_dir {
+                    let ts = Local::now().format("%H%M%S%.3f");
+                    let path = dir.join(format!("frame_{}.jpg", ts));
+                    
+                    match File::create(&path) {
+                        Ok(mut file) => {
+                            if let Err(e) = file.write_all(jpeg) {
+                                error!("Failed to write frame: {}", e);
+                            } else {
+                                debug!("Saved frame to {:?}", path);
                             }
-                        } else {
-                            debug!("Video frame received but no turn directory is open");
+                        }
+                        Err(e) => {
+                            error!("Failed to create frame file: {}", e);
                         }
                     }
+                } else {
+                    debug!("Video frame received but no turn directory is open");
                 }
-                
-                // Handle activityEnd
-                if json.get("activityEnd").is_some() && self.pending_audio_close_for_turn {
+            }
+            
+            // Handle activityEnd
+            WsOutbound::ActivityEnd => {
+                if self.pending_audio_close_for_turn {
                     // This is the end of an audio turn, close the directory
                     debug!("Closing audio turn directory after activityEnd");
                     self.cur_dir = None;
                     self.pending_audio_close_for_turn = false;
-                } else if json.get("activityEnd").is_some() && self.cur_dir.is_some() {
+                } else if self.cur_dir.is_some() {
                     // This is the end of a video turn
                     debug!("Closing video turn directory after activityEnd");
                     self.cur_dir = None;
@@ -0,0 +1,204 @@
+//! Filters between streamed model text and the overlay
+//!
+//! Text chunks of a response go through a [`ResponsePipeline`]. While the
+//! response streams, sentinels such as [`NOTHING`] are removed, including
+//! ones split across chunks: text that could be the start of a sentinel is
+//! held back until the next chunk shows what it is. When the response is
+//! finished, whitespace-only text is dropped and the [`ResponseFilter`] chain
+//! runs, which may rewrite or drop the response. At the end of each turn the
+//! pipeline tells whether the model stayed silent or answered.
+
+use tracing::debug;
+
+/// What the model says when there is nothing worth saying
+pub const NOTHING: &str = "<nothing>";
+
+/// A step applied to every finished response
+pub trait ResponseFilter: Send {
+    /// Name for logs
+    fn name(&self) -> &'static str;
+
+    /// The response to show instead of `text`, or `None` to drop it
+    fn filter(&mut self, text: String) -> Option<String>;
+}
+
+/// How a turn ended up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnOutcome {
+    /// Only sentinels, whitespace or filtered responses
+    Silent,
+    /// At least one response was shown
+    Answered,
+}
+
+/// Drops an answer that repeats the previous one, as the model tends to do
+/// when the screen does not change
+#[derive(Debug, Default)]
+pub struct DropRepeats {
+    last: Option<String>,
+}
+
+impl ResponseFilter for DropRepeats {
+    fn name(&self) -> &'static str {
+        "drop repeats"
+    }
+
+    fn filter(&mut self, text: String) -> Option<String> {
+        // Ignore differences in case and spacing
+        let key = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        if self.last.as_ref() == Some(&key) {
+            return None;
+        }
+        self.last = Some(key);
+        Some(text)
+    }
+}
+
+/// Cleans up streamed responses and classifies turns
+pub struct ResponsePipeline {
+    sentinels: Vec<&'static str>,
+    filters: Vec<Box<dyn ResponseFilter>>,
+    /// Raw text of the response being streamed
+    text: String,
+    /// Whether any text arrived in this turn
+    turn_started: bool,
+    /// Whether any response was shown in this turn
+    answered: bool,
+}
+
+impl ResponsePipeline {
+    /// A pipeline removing `sentinels` from the text, with no filters yet
+    pub fn new(sentinels: &[&'static str]) -> Self {
+        Self {
+            sentinels: sentinels.to_vec(),
+            filters: Vec::new(),
+            text: String::new(),
+            turn_started: false,
+            answered: false,
+        }
+    }
+
+    /// Add a filter, run after the ones added before it
+    pub fn with_filter(mut self, filter: impl ResponseFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Add a chunk and return the text to show so far, if there is any.
+    pub fn push(&mut self, chunk: &str) -> Option<String> {
+        self.turn_started = true;
+        self.text.push_str(chunk);
+        let visible = self.visible(true);
+        let visible = visible.trim();
+        (!visible.is_empty()).then(|| visible.to_string())
+    }
+
+    /// End the current response and return it if it should be shown.
+    pub fn finish(&mut self) -> Option<String> {
+        let text = self.visible(false);
+        self.text.clear();
+        let mut text = text.trim().to_string();
+        if text.is_empty() {
+            return None;
+        }
+
+        for filter in &mut self.filters {
+            match filter.filter(text) {
+                Some(filtered) => text = filtered,
+                None => {
+                    debug!("Response dropped by {} filter", filter.name());
+                    return None;
+                }
+            }
+        }
+        if text.trim().is_empty() {
+            return None;
+        }
+        self.answered = true;
+        Some(text)
+    }
+
+    /// Close the turn; `None` if no text arrived since the last turn ended.
+    ///
+    /// Call [`ResponsePipeline::finish`] first, text still pending is discarded.
+    pub fn end_turn(&mut self) -> Option<TurnOutcome> {
+        self.text.clear();
+        let outcome = match (self.turn_started, self.answered) {
+            (false, _) => None,
+            (true, false) => Some(TurnOutcome::Silent),
+            (true, true) => Some(TurnOutcome::Answered),
+        };
+        self.turn_started = false;
+        self.answered = false;
+        outcome
+    }
+
+    /// The text without sentinels. While `streaming`, a trailing partial
+    /// sentinel is held back.
+    fn visible(&self, streaming: bool) -> String {
+        let mut text = self.text.clone();
+        for sentinel in &self.sentinels {
+            text = text.replace(sentinel, "");
+        }
+        if streaming {
+            let held = self
+                .sentinels
+                .iter()
+                .map(|sentinel| partial_sentinel_len(&text, sentinel))
+                .max()
+                .unwrap_or(0);
+            text.truncate(text.len() - held);
+        }
+        text
+    }
+}
+
+/// Length of the longest end of `text` that starts `sentinel` without completing it
+fn partial_sentinel_len(text: &str, sentinel: &str) -> usize {
+    (1..sentinel.len())
+        .rev()
+        .find(|&len| sentinel.is_char_boundary(len) && text.ends_with(&sentinel[..len]))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentinel_split_across_chunks() {
+        let mut pipeline = ResponsePipeline::new(&[NOTHING]);
+        assert_eq!(pipeline.push("<no"), None);
+        assert_eq!(pipeline.push("thing"), None);
+        assert_eq!(pipeline.push(">\n"), None);
+        assert_eq!(pipeline.finish(), None);
+        assert_eq!(pipeline.end_turn(), Some(TurnOutcome::Silent));
+
+        // Text that only looks like a sentinel start is shown once it is not one
+        assert_eq!(pipeline.push("use a <"), Some("use a".to_string()));
+        assert_eq!(pipeline.push(" b"), Some("use a < b".to_string()));
+        assert_eq!(pipeline.push(" <"), Some("use a < b".to_string()));
+        assert_eq!(pipeline.finish(), Some("use a < b <".to_string()));
+        assert_eq!(pipeline.end_turn(), Some(TurnOutcome::Answered));
+        assert_eq!(pipeline.end_turn(), None);
+    }
+
+    #[test]
+    fn test_whitespace_and_repeats_are_silent() {
+        let mut pipeline = ResponsePipeline::new(&[NOTHING]).with_filter(DropRepeats::default());
+        pipeline.push(" \n ");
+        assert_eq!(pipeline.finish(), None);
+        assert_eq!(pipeline.end_turn(), Some(TurnOutcome::Silent));
+
+        pipeline.push("Use a hash map.");
+        assert_eq!(pipeline.finish(), Some("Use a hash map.".to_string()));
+        pipeline.end_turn();
+
+        pipeline.push("use a  hash map.");
+        assert_eq!(pipeline.finish(), None);
+        assert_eq!(pipeline.end_turn(), Some(TurnOutcome::Silent));
+
+        pipeline.push("Now sort it.");
+        assert_eq!(pipeline.finish(), Some("Now sort it.".to_string()));
+    }
+}
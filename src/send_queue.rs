@@ -0,0 +1,312 @@
+//! Bounded, priority-aware queue for outbound realtime messages
+//!
+//! Activity markers split the queue into segments that are sent strictly in
+//! order, so a turn always carries the media captured for it. Within a
+//! segment audio and control messages go before video, and video frames are
+//! coalesced so only the newest one waits. Media that sat in the queue past
+//! its deadline is dropped instead of being sent late.
+//!
+//! When the queue is full the oldest video frame makes room first, then the
+//! oldest audio chunk. Activity markers and control messages are never
+//! dropped.
+
+use crate::media_event::WsOutbound;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Limits for the outbound queue
+#[derive(Debug, Clone, Copy)]
+pub struct SendQueueConfig {
+    /// Messages held before media starts being dropped
+    pub capacity: usize,
+    /// Audio older than this is dropped rather than sent
+    pub audio_deadline: Duration,
+    /// Video frames older than this are dropped rather than sent
+    pub video_deadline: Duration,
+}
+
+impl Default for SendQueueConfig {
+    fn default() -> Self {
+        Self {
+            // ~10s of 40ms audio chunks
+            capacity: 256,
+            audio_deadline: Duration::from_secs(2),
+            // Frames arrive every 500ms, anything older is already superseded
+            video_deadline: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Queue depth and how many messages never made it to the socket
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SendQueueStats {
+    pub depth: usize,
+    /// Video frames replaced by a newer frame while queued
+    pub coalesced_video: u64,
+    /// Video frames dropped because the queue was full
+    pub dropped_video: u64,
+    /// Audio chunks dropped because the queue was full
+    pub dropped_audio: u64,
+    /// Media dropped for waiting past its deadline
+    pub expired: u64,
+}
+
+impl SendQueueStats {
+    /// Messages that were queued but will never be sent
+    pub fn lost(&self) -> u64 {
+        self.coalesced_video + self.dropped_video + self.dropped_audio + self.expired
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    /// Activity start/end, keeps its position relative to everything else
+    Marker,
+    /// Text, tool responses, config updates and client content
+    Control,
+    Audio,
+    Video,
+}
+
+impl Class {
+    fn of(msg: &WsOutbound) -> Self {
+        match msg {
+            WsOutbound::ActivityStart | WsOutbound::ActivityEnd => Class::Marker,
+            WsOutbound::Audio(_) => Class::Audio,
+            WsOutbound::Video(_) => Class::Video,
+            WsOutbound::Text(_)
+            | WsOutbound::ConfigUpdate(_)
+            | WsOutbound::ToolResponse(_)
+            | WsOutbound::ClientContent(_) => Class::Control,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    msg: WsOutbound,
+    class: Class,
+    queued_at: Instant,
+}
+
+/// Outbound messages waiting for the socket
+#[derive(Debug)]
+pub struct SendQueue {
+    config: SendQueueConfig,
+    entries: VecDeque<Entry>,
+    stats: SendQueueStats,
+}
+
+impl SendQueue {
+    pub fn new(config: SendQueueConfig) -> Self {
+        Self {
+            config,
+            entries: VecDeque::new(),
+            stats: SendQueueStats::default(),
+        }
+    }
+
+    pub fn push(&mut self, msg: WsOutbound) {
+        self.push_at(msg, Instant::now());
+    }
+
+    fn push_at(&mut self, msg: WsOutbound, now: Instant) {
+        let class = Class::of(&msg);
+
+        // Replace a frame still waiting in the current segment
+        if class == Class::Video {
+            let waiting = self
+                .entries
+                .iter_mut()
+                .rev()
+                .take_while(|entry| entry.class != Class::Marker)
+                .find(|entry| entry.class == Class::Video);
+            if let Some(entry) = waiting {
+                entry.msg = msg;
+                entry.queued_at = now;
+                self.stats.coalesced_video += 1;
+                return;
+            }
+        }
+
+        if self.entries.len() >= self.config.capacity {
+            if let Some(index) = self.position(Class::Video) {
+                self.entries.remove(index);
+                self.stats.dropped_video += 1;
+            } else if class == Class::Video {
+                self.stats.dropped_video += 1;
+                return;
+            } else if let Some(index) = self.position(Class::Audio) {
+                self.entries.remove(index);
+                self.stats.dropped_audio += 1;
+            }
+            // Only markers and control messages left: let them through
+        }
+
+        self.entries.push_back(Entry {
+            msg,
+            class,
+            queued_at: now,
+        });
+    }
+
+    /// Next message to send, skipping media past its deadline
+    pub fn pop(&mut self) -> Option<WsOutbound> {
+        self.pop_at(Instant::now())
+    }
+
+    fn pop_at(&mut self, now: Instant) -> Option<WsOutbound> {
+        loop {
+            // Everything before the next marker belongs to the current segment
+            let segment_end = self.position(Class::Marker).unwrap_or(self.entries.len());
+            let index = self
+                .entries
+                .iter()
+                .take(segment_end)
+                .position(|entry| entry.class != Class::Video)
+                .unwrap_or(0);
+            let entry = self.entries.remove(index)?;
+
+            let deadline = match entry.class {
+                Class::Audio => Some(self.config.audio_deadline),
+                Class::Video => Some(self.config.video_deadline),
+                Class::Marker | Class::Control => None,
+            };
+            match deadline {
+                Some(deadline) if now.duration_since(entry.queued_at) > deadline => {
+                    self.stats.expired += 1;
+                }
+                _ => return Some(entry.msg),
+            }
+        }
+    }
+
+    pub fn stats(&self) -> SendQueueStats {
+        SendQueueStats {
+            depth: self.entries.len(),
+            ..self.stats
+        }
+    }
+
+    fn position(&self, class: Class) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.class == class)
+    }
+}
+
+/// A [`SendQueue`] filled by one task and drained by the socket writer
+#[derive(Debug)]
+pub struct SharedSendQueue {
+    queue: Mutex<SendQueue>,
+    ready: Notify,
+    closed: AtomicBool,
+}
+
+impl SharedSendQueue {
+    pub fn new(config: SendQueueConfig) -> Self {
+        Self {
+            queue: Mutex::new(SendQueue::new(config)),
+            ready: Notify::new(),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    pub fn push(&self, msg: WsOutbound) {
+        self.queue.lock().unwrap().push(msg);
+        self.ready.notify_one();
+    }
+
+    /// No more messages will be pushed; `pop` returns `None` once drained
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.ready.notify_one();
+    }
+
+    /// Wait for the next message to send. Cancel safe.
+    pub async fn pop(&self) -> Option<WsOutbound> {
+        loop {
+            if let Some(msg) = self.queue.lock().unwrap().pop() {
+                return Some(msg);
+            }
+            if self.closed.load(Ordering::SeqCst) {
+                return None;
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    pub fn stats(&self) -> SendQueueStats {
+        self.queue.lock().unwrap().stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(queue: &mut SendQueue, now: Instant) -> Vec<String> {
+        std::iter::from_fn(|| queue.pop_at(now))
+            .map(|msg| match msg {
+                WsOutbound::ActivityStart => "start".to_string(),
+                WsOutbound::ActivityEnd => "end".to_string(),
+                WsOutbound::Audio(data) => format!("a{}", data[0]),
+                WsOutbound::Video(data) => format!("v{}", data[0]),
+                other => format!("{:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_audio_before_video_within_segment() {
+        let mut queue = SendQueue::new(SendQueueConfig::default());
+        let now = Instant::now();
+        queue.push_at(WsOutbound::ActivityStart, now);
+        queue.push_at(WsOutbound::Video(vec![1]), now);
+        queue.push_at(WsOutbound::Audio(vec![1]), now);
+        queue.push_at(WsOutbound::Video(vec![2]), now);
+        queue.push_at(WsOutbound::Audio(vec![2]), now);
+        queue.push_at(WsOutbound::ActivityEnd, now);
+        queue.push_at(WsOutbound::Video(vec![3]), now);
+
+        assert_eq!(kinds(&mut queue, now), ["start", "a1", "a2", "v2", "end", "v3"]);
+        assert_eq!(queue.stats().coalesced_video, 1);
+    }
+
+    #[test]
+    fn test_full_queue_drops_media_but_keeps_markers() {
+        let mut queue = SendQueue::new(SendQueueConfig {
+            capacity: 3,
+            ..Default::default()
+        });
+        let now = Instant::now();
+        queue.push_at(WsOutbound::Video(vec![1]), now);
+        queue.push_at(WsOutbound::Audio(vec![1]), now);
+        queue.push_at(WsOutbound::Audio(vec![2]), now);
+        queue.push_at(WsOutbound::Audio(vec![3]), now); // drops v1
+        queue.push_at(WsOutbound::ActivityEnd, now); // drops a1
+        queue.push_at(WsOutbound::ActivityStart, now); // drops a2
+        queue.push_at(WsOutbound::ActivityEnd, now); // drops a3
+        queue.push_at(WsOutbound::Text("hi".into()), now); // nothing left to drop
+
+        assert_eq!(kinds(&mut queue, now), ["end", "start", "end", "Text(\"hi\")"]);
+        let stats = queue.stats();
+        assert_eq!((stats.dropped_video, stats.dropped_audio), (1, 3));
+    }
+
+    #[test]
+    fn test_stale_media_expires() {
+        let mut queue = SendQueue::new(SendQueueConfig::default());
+        let then = Instant::now();
+        queue.push_at(WsOutbound::Video(vec![1]), then);
+        queue.push_at(WsOutbound::Audio(vec![1]), then);
+        queue.push_at(WsOutbound::ActivityEnd, then);
+        queue.push_at(WsOutbound::Audio(vec![2]), then + Duration::from_millis(1500));
+
+        let now = then + Duration::from_millis(1600);
+        assert_eq!(kinds(&mut queue, now), ["a1", "end", "a2"]);
+        assert_eq!(queue.stats().expired, 1);
+        assert_eq!(queue.stats().depth, 0);
+    }
+}
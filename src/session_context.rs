@@ -0,0 +1,113 @@
+//! Prior context to seed a new session with
+//!
+//! Context files are either JSON, a list of `{"role": "user"|"model", "text": ...}`
+//! turns as written by [`save`], or plain text such as notes or a summary,
+//! which becomes a single user turn. The turns are sent as `clientContent`
+//! with `turnComplete: false` right after setup, so the model has them in its
+//! history without answering them.
+
+use crate::gemini::{Content, Part};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Most recent turns kept when saving a session
+const MAX_SAVED_TURNS: usize = 40;
+
+/// One turn of saved conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextTurn {
+    /// `user` or `model`
+    pub role: String,
+    pub text: String,
+}
+
+impl ContextTurn {
+    pub fn user(text: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            text: text.into(),
+        }
+    }
+
+    pub fn model(text: impl Into<String>) -> Self {
+        Self {
+            role: "model".to_string(),
+            text: text.into(),
+        }
+    }
+}
+
+impl From<ContextTurn> for Content {
+    fn from(turn: ContextTurn) -> Self {
+        Content {
+            role: Some(turn.role),
+            parts: vec![Part {
+                text: Some(turn.text),
+                ..Default::default()
+            }],
+        }
+    }
+}
+
+/// Read the turns from a context file.
+pub fn load(path: &Path) -> Result<Vec<ContextTurn>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read context file {}", path.display()))?;
+    parse(&text).with_context(|| format!("Invalid context file {}", path.display()))
+}
+
+fn parse(text: &str) -> Result<Vec<ContextTurn>> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !text.starts_with('[') {
+        return Ok(vec![ContextTurn::user(text)]);
+    }
+
+    let turns: Vec<ContextTurn> = serde_json::from_str(text)?;
+    if let Some(turn) = turns.iter().find(|t| t.role != "user" && t.role != "model") {
+        bail!("Unknown role `{}`, expected `user` or `model`", turn.role);
+    }
+    Ok(turns)
+}
+
+/// Write the most recent turns so a later run can pick up from them.
+pub fn save(path: &Path, turns: &[ContextTurn]) -> Result<()> {
+    let recent = &turns[turns.len().saturating_sub(MAX_SAVED_TURNS)..];
+    let json = serde_json::to_string_pretty(recent)?;
+    std::fs::write(path, json)
+        .with_context(|| format!("Failed to write session file {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_notes_and_turns() {
+        assert_eq!(
+            parse("  working on the parser rewrite\n").unwrap(),
+            [ContextTurn::user("working on the parser rewrite")]
+        );
+        assert_eq!(
+            parse(r#"[{"role":"user","text":"hi"},{"role":"model","text":"hello"}]"#).unwrap(),
+            [ContextTurn::user("hi"), ContextTurn::model("hello")]
+        );
+        assert!(parse(r#"[{"role":"system","text":"x"}]"#).is_err());
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_save_keeps_recent_turns() {
+        let path = std::env::temp_dir().join(format!("rholive-context-{}.json", std::process::id()));
+        let turns: Vec<_> = (0..50).map(|i| ContextTurn::user(i.to_string())).collect();
+        save(&path, &turns).unwrap();
+
+        let loaded = load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.len(), MAX_SAVED_TURNS);
+        assert_eq!(loaded[0].text, "10");
+    }
+}
@@ -6,9 +6,8 @@
 //! 
 //! This allows us to send everything immediately with no client-side queuing.
 
+use crate::gemini::{ActivityHandling, RealtimeInputConfig};
 use crate::media_event::{WsOutbound, MediaEvent};
-use base64::Engine;
-use serde_json::json;
 use std::collections::VecDeque;
 use std::time::{Instant, Duration};
 use tokio::sync::broadcast;
@@ -35,6 +34,10 @@ pub enum Event {
     Frame { jpeg: Vec<u8>, hash: u64 },
     /// Response received from Gemini
     ResponseReceived,
+    /// Gemini cut off the response in progress
+    ResponseInterrupted,
+    /// Gemini finished its turn
+    TurnComplete,
 }
 
 /// FSM states
@@ -85,6 +88,7 @@ pub struct SimpleTurnFsm {
     last_turn_was_video: bool,           // what kind of turn ended last?
     pending_turn_types: VecDeque<bool>,  // queue parallels turn_end_times: true=video
     need_activity_reset: bool,           // do we owe Gemini a reset to NO_INTERRUPTION?
+    response_accounted: bool,            // has the current server turn already popped its pending turn?
     // ===========================================================
 }
 
@@ -105,6 +109,7 @@ impl SimpleTurnFsm {
             last_turn_was_video: false,
             pending_turn_types: VecDeque::new(),
             need_activity_reset: false,
+            response_accounted: false,
         }
     }
     
@@ -143,7 +148,7 @@ impl SimpleTurnFsm {
                 if pending_video {
                     // Cancel the video generation that's still running.
                     info!("🚫 Interrupting pending video turn(s) for audio");
-                    self.send_activity_handling_update("START_OF_ACTIVITY_INTERRUPTS");
+                    self.send_activity_handling_update(ActivityHandling::StartOfActivityInterrupts);
                     self.need_activity_reset = true;
                 }
                 
@@ -200,7 +205,7 @@ impl SimpleTurnFsm {
                 if pending_video {
                     // Cancel the video generation that's still running.
                     info!("🚫 Interrupting pending video turn(s) for audio");
-                    self.send_activity_handling_update("START_OF_ACTIVITY_INTERRUPTS");
+                    self.send_activity_handling_update(ActivityHandling::StartOfActivityInterrupts);
                     self.need_activity_reset = true;
                 }
                 
@@ -257,7 +262,7 @@ impl SimpleTurnFsm {
                 info!("🎤 Ending audio turn with fresh frame");
                 if self.need_activity_reset {
                     // Flush the audio turn, then revert to NO_INTERRUPTION
-                    self.send_activity_handling_update("NO_INTERRUPTION");
+                    self.send_activity_handling_update(ActivityHandling::NoInterruption);
                     self.need_activity_reset = false;
                 }
                 self.send_activity_end();
@@ -279,7 +284,7 @@ impl SimpleTurnFsm {
                 }
                 if self.need_activity_reset {
                     // Flush the audio turn, then revert to NO_INTERRUPTION
-                    self.send_activity_handling_update("NO_INTERRUPTION");
+                    self.send_activity_handling_update(ActivityHandling::NoInterruption);
                     self.need_activity_reset = false;
                 }
                 self.send_activity_end();
@@ -296,9 +301,9 @@ impl SimpleTurnFsm {
             }
             
             // Response received - calculate latency
-            (_, Event::ResponseReceived) => {
-                if let Some((turn_end_time, _was_video)) = self.turn_end_times.pop_front() {
-                    self.pending_turn_types.pop_front();
+            (_, Event::ResponseReceived) if !self.response_accounted => {
+                self.response_accounted = true;
+                if let Some((turn_end_time, _was_video)) = self.pop_pending_turn() {
                     let now = Instant::now();
                     let latency = now.duration_since(turn_end_time);
                     let latency_ms = latency.as_millis() as u64;
@@ -314,6 +319,24 @@ impl SimpleTurnFsm {
                 }
             }
             
+            // Interrupted response will never complete - drop its turn without a latency sample
+            (_, Event::ResponseInterrupted) if !self.response_accounted => {
+                self.response_accounted = true;
+                if let Some((_, was_video)) = self.pop_pending_turn() {
+                    info!("🚫 Dropped interrupted {} turn from latency tracking",
+                          if was_video { "video" } else { "audio" });
+                }
+            }
+            
+            // Turn over - make sure it consumed exactly one pending turn
+            (_, Event::TurnComplete) => {
+                if !self.response_accounted {
+                    debug!("Turn completed without a generation, dropping its pending turn");
+                    self.pop_pending_turn();
+                }
+                self.response_accounted = false;
+            }
+            
             // Ignore duplicates and invalid transitions
             _ => {}
         }
@@ -324,6 +347,11 @@ impl SimpleTurnFsm {
         std::mem::take(&mut self.outbound)
     }
     
+    /// Most recent unique screen frame
+    pub fn last_frame(&self) -> Option<&[u8]> {
+        self.last_frame_data.as_deref()
+    }
+    
     /// Check if we've been waiting too long for forced frame
     pub fn check_force_frame_timeout(&mut self) {
         if let State::WaitingForForcedFrame = self.state {
@@ -339,7 +367,7 @@ impl SimpleTurnFsm {
                     // End the turn
                     if self.need_activity_reset {
                         // Flush the audio turn, then revert to NO_INTERRUPTION
-                        self.send_activity_handling_update("NO_INTERRUPTION");
+                        self.send_activity_handling_update(ActivityHandling::NoInterruption);
                         self.need_activity_reset = false;
                     }
                     self.send_activity_end();
@@ -355,45 +383,33 @@ impl SimpleTurnFsm {
     
     // === Helper methods ===
     
+    /// Remove the oldest turn still waiting for a response
+    fn pop_pending_turn(&mut self) -> Option<(Instant, bool)> {
+        self.pending_turn_types.pop_front();
+        self.turn_end_times.pop_front()
+    }
+    
     fn send_activity_start(&mut self) {
-        let msg = json!({ "activityStart": {} });
-        self.outbound.push(WsOutbound::Json(msg));
+        self.outbound.push(WsOutbound::ActivityStart);
     }
     
     fn send_activity_end(&mut self) {
-        let msg = json!({ "activityEnd": {} });
-        self.outbound.push(WsOutbound::Json(msg));
+        self.outbound.push(WsOutbound::ActivityEnd);
     }
     
     fn send_audio(&mut self, pcm: &[u8]) {
-        let msg = json!({
-            "audio": {
-                "data": base64::engine::general_purpose::STANDARD.encode(pcm),
-                "mimeType": "audio/pcm;rate=16000"
-            }
-        });
-        self.outbound.push(WsOutbound::Json(msg));
+        self.outbound.push(WsOutbound::Audio(pcm.to_vec()));
     }
     
     fn send_video(&mut self, jpeg: &[u8]) {
-        let msg = json!({
-            "video": {
-                "data": base64::engine::general_purpose::STANDARD.encode(jpeg),
-                "mimeType": "image/jpeg"
-            }
-        });
-        self.outbound.push(WsOutbound::Json(msg));
+        self.outbound.push(WsOutbound::Video(jpeg.to_vec()));
     }
     
-    fn send_activity_handling_update(&mut self, mode: &str) {
-        let msg = json!({
-            "setup": {
-                "realtimeInputConfig": {
-                    "activityHandling": mode
-                }
-            }
-        });
-        self.outbound.push(WsOutbound::Json(msg));
+    fn send_activity_handling_update(&mut self, mode: ActivityHandling) {
+        self.outbound.push(WsOutbound::ConfigUpdate(RealtimeInputConfig {
+            activity_handling: Some(mode),
+            ..Default::default()
+        }));
     }
     
     fn print_latency_report(&self, current_latency_ms: u64) {
@@ -416,4 +432,39 @@ impl SimpleTurnFsm {
         }
         println!("====================================\n");
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(hash: u64) -> Event {
+        Event::Frame { jpeg: vec![0xff], hash }
+    }
+
+    #[test]
+    fn test_interrupted_turn_does_not_skew_latency_queue() {
+        let (media_tx, _media_rx) = broadcast::channel(8);
+        let mut fsm = SimpleTurnFsm::new(media_tx);
+
+        // One video turn, then an audio turn that interrupts it
+        fsm.on_event(frame(1));
+        fsm.on_event(frame(2));
+        fsm.on_event(Event::SpeechStart);
+        fsm.on_event(Event::SpeechEnd);
+        fsm.on_event(frame(3));
+        assert_eq!(fsm.turn_end_times.len(), 2);
+
+        // The video response is cut off, then the audio turn is answered
+        fsm.on_event(Event::ResponseInterrupted);
+        fsm.on_event(Event::TurnComplete);
+        assert_eq!(fsm.turn_end_times.len(), 1);
+        assert!(fsm.recent_latencies.is_empty());
+
+        fsm.on_event(Event::ResponseReceived);
+        fsm.on_event(Event::TurnComplete);
+        assert!(fsm.turn_end_times.is_empty());
+        assert!(fsm.pending_turn_types.is_empty());
+        assert_eq!(fsm.recent_latencies.len(), 1);
+    }
+}
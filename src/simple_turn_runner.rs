@@ -1,5 +1,6 @@
 //! Simple Turn Runner - Connects media events to the FSM and WebSocket
 
+use crate::escalation::EscalationContext;
 use crate::media_event::{MediaEvent, WsOutbound, WsInbound, Outgoing};
 use crate::simple_turn_fsm::{SimpleTurnFsm, Event};
 use crate::recorder::TurnRecorder;
@@ -15,6 +16,7 @@ pub async fn run(
     ws_out_tx: mpsc::UnboundedSender<WsOutbound>,
     mut ws_in_rx: mpsc::UnboundedReceiver<WsInbound>,
     record: bool,
+    escalation: Option<EscalationContext>,
 ) {
     let mut fsm = SimpleTurnFsm::new(media_tx);
     let mut stats_ticker = interval(Duration::from_secs(30));
@@ -55,6 +57,11 @@ pub async fn run(
                     
                     fsm.on_event(Event::Frame { jpeg, hash });
                     
+                    // Escalated questions come with the latest screen
+                    if let (Some(context), Some(frame)) = (&escalation, fsm.last_frame()) {
+                        context.set_frame(frame.to_vec());
+                    }
+                    
                     // Send any generated messages immediately
                     for msg in fsm.drain_messages() {
                         recorder.on_ws(&msg);  // Record before sending
@@ -108,6 +115,13 @@ pub async fn run(
                         // Notify FSM to calculate latency
                         fsm.on_event(Event::ResponseReceived);
                     }
+                    WsInbound::Interrupted => {
+                        info!("Response interrupted");
+                        fsm.on_event(Event::ResponseInterrupted);
+                    }
+                    WsInbound::TurnComplete => {
+                        fsm.on_event(Event::TurnComplete);
+                    }
                     _ => {}
                 }
             }
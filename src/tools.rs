@@ -0,0 +1,376 @@
+//! Function-calling tools exposed to the Live model
+//!
+//! Tools are registered in a [`ToolRegistry`], which provides the function
+//! declarations for the setup message and answers incoming tool calls. Each
+//! function call runs in its own task and its result is sent back as a
+//! `toolResponse` matched by call id.
+//!
+//! Tools declared as non-blocking let the conversation continue while they
+//! run; their responses carry a scheduling hint telling the model how to
+//! react. Calls still in flight are aborted when the server cancels them.
+
+use crate::gemini::{
+    FunctionBehavior, FunctionCall, FunctionDeclaration, FunctionResponse,
+    FunctionResponseScheduling, ToolCall, ToolDeclaration, ToolResponse,
+};
+use crate::media_event::WsOutbound;
+use futures_util::future::BoxFuture;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+use tracing::{debug, info, warn};
+
+/// A function the model can call
+pub trait Tool: Send + Sync {
+    /// Function name as declared to the model
+    fn name(&self) -> &str;
+
+    /// What the function does, used by the model to decide when to call it
+    fn description(&self) -> &str;
+
+    /// JSON schema of the arguments, or `None` if the function takes none
+    fn parameters(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Whether the model should keep talking while this tool runs
+    fn behavior(&self) -> FunctionBehavior {
+        FunctionBehavior::Blocking
+    }
+
+    /// How the model should react to the result of a non-blocking call
+    fn scheduling(&self) -> FunctionResponseScheduling {
+        FunctionResponseScheduling::WhenIdle
+    }
+
+    /// Execute the function with the arguments supplied by the model
+    fn invoke(&self, args: serde_json::Value) -> BoxFuture<'static, anyhow::Result<serde_json::Value>>;
+}
+
+/// Registered tools keyed by function name
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, Arc<dyn Tool>>,
+    /// Calls still running, keyed by call id
+    in_flight: Arc<Mutex<HashMap<String, AbortHandle>>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a tool, replacing any previous tool with the same name
+    pub fn register<T: Tool + 'static>(&mut self, tool: T) {
+        self.tools.insert(tool.name().to_string(), Arc::new(tool));
+    }
+
+    /// Names of the registered tools, sorted
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tools.keys().map(String::as_str)
+    }
+
+    /// Function declarations for the setup message, `None` if nothing is registered
+    pub fn declaration(&self) -> Option<ToolDeclaration> {
+        self.declaration_where(|_| true)
+    }
+
+    /// Declarations of the tools whose names pass `enabled`, `None` if there are none
+    pub fn declaration_where(&self, enabled: impl Fn(&str) -> bool) -> Option<ToolDeclaration> {
+        let function_declarations: Vec<_> = self
+            .tools
+            .values()
+            .filter(|tool| enabled(tool.name()))
+            .map(|tool| FunctionDeclaration {
+                name: tool.name().to_string(),
+                description: tool.description().to_string(),
+                parameters: tool.parameters(),
+                behavior: match tool.behavior() {
+                    FunctionBehavior::Blocking => None,
+                    behavior => Some(behavior),
+                },
+            })
+            .collect();
+        if function_declarations.is_empty() {
+            return None;
+        }
+
+        Some(ToolDeclaration {
+            function_declarations,
+            ..Default::default()
+        })
+    }
+
+    /// Run every function call of a tool call concurrently, sending each
+    /// result back as its own `toolResponse` as soon as it is ready.
+    pub fn dispatch(&self, tool_call: ToolCall, tx: mpsc::UnboundedSender<WsOutbound>) {
+        // Hold the lock while spawning so a fast task cannot finish before it is tracked
+        let mut in_flight = self.in_flight.lock().unwrap();
+
+        for call in tool_call.function_calls {
+            let tool = self.tools.get(&call.name).cloned();
+            let tx = tx.clone();
+            let id = call.id.clone();
+            let tracker = self.in_flight.clone();
+
+            let task = tokio::spawn(async move {
+                let scheduling = tool
+                    .as_ref()
+                    .filter(|tool| tool.behavior() == FunctionBehavior::NonBlocking)
+                    .map(|tool| tool.scheduling());
+                let response = run_call(tool, &call).await;
+                tracker.lock().unwrap().remove(&call.id);
+
+                let tool_response = ToolResponse {
+                    function_responses: vec![FunctionResponse {
+                        id: call.id,
+                        name: call.name,
+                        response,
+                        scheduling,
+                    }],
+                };
+
+                if tx.send(WsOutbound::ToolResponse(tool_response)).is_err() {
+                    warn!("Dropping tool response - outbound channel closed");
+                }
+            });
+
+            in_flight.insert(id, task.abort_handle());
+        }
+    }
+
+    /// Abort calls the server no longer wants answered. Their responses are never sent.
+    pub fn cancel(&self, ids: &[String]) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        for id in ids {
+            match in_flight.remove(id) {
+                Some(handle) => {
+                    info!("🚫 Cancelling tool call {}", id);
+                    handle.abort();
+                }
+                None => debug!("Tool call {} already finished, nothing to cancel", id),
+            }
+        }
+    }
+}
+
+/// Invoke a single call, turning failures into an error payload for the model.
+async fn run_call(tool: Option<Arc<dyn Tool>>, call: &FunctionCall) -> serde_json::Value {
+    let Some(tool) = tool else {
+        warn!("Model called unknown function '{}'", call.name);
+        return json!({ "error": format!("unknown function '{}'", call.name) });
+    };
+
+    info!("🔧 Running tool '{}' (id: {})", call.name, call.id);
+    match tool.invoke(call.args.clone()).await {
+        Ok(output) => json!({ "output": output }),
+        Err(e) => {
+            warn!("Tool '{}' failed: {}", call.name, e);
+            json!({ "error": e.to_string() })
+        }
+    }
+}
+
+/// Reports the local date and time
+pub struct CurrentTime;
+
+impl Tool for CurrentTime {
+    fn name(&self) -> &str {
+        "get_current_time"
+    }
+
+    fn description(&self) -> &str {
+        "Returns the user's current local date and time."
+    }
+
+    fn invoke(&self, _args: serde_json::Value) -> BoxFuture<'static, anyhow::Result<serde_json::Value>> {
+        Box::pin(async move { Ok(json!(chrono::Local::now().to_rfc3339())) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gemini::ClientMessage;
+
+    struct Echo;
+
+    impl Tool for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "Echoes its arguments"
+        }
+
+        fn invoke(&self, args: serde_json::Value) -> BoxFuture<'static, anyhow::Result<serde_json::Value>> {
+            Box::pin(async move { Ok(args) })
+        }
+    }
+
+    struct Slow;
+
+    impl Tool for Slow {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        fn description(&self) -> &str {
+            "Never finishes in time"
+        }
+
+        fn behavior(&self) -> FunctionBehavior {
+            FunctionBehavior::NonBlocking
+        }
+
+        fn invoke(&self, _args: serde_json::Value) -> BoxFuture<'static, anyhow::Result<serde_json::Value>> {
+            Box::pin(async move {
+                tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+                Ok(json!(null))
+            })
+        }
+    }
+
+    struct Notify;
+
+    impl Tool for Notify {
+        fn name(&self) -> &str {
+            "notify"
+        }
+
+        fn description(&self) -> &str {
+            "Finishes in the background without needing a reply"
+        }
+
+        fn behavior(&self) -> FunctionBehavior {
+            FunctionBehavior::NonBlocking
+        }
+
+        fn scheduling(&self) -> FunctionResponseScheduling {
+            FunctionResponseScheduling::Silent
+        }
+
+        fn invoke(&self, _args: serde_json::Value) -> BoxFuture<'static, anyhow::Result<serde_json::Value>> {
+            Box::pin(async move { Ok(json!("sent")) })
+        }
+    }
+
+    fn call(id: &str, name: &str) -> FunctionCall {
+        FunctionCall {
+            id: id.to_string(),
+            name: name.to_string(),
+            args: json!({ "x": 1 }),
+        }
+    }
+
+    #[test]
+    fn test_declaration() {
+        let mut registry = ToolRegistry::new();
+        assert!(registry.declaration().is_none());
+
+        registry.register(Echo);
+        let declaration = registry.declaration().unwrap();
+        assert_eq!(declaration.function_declarations.len(), 1);
+        assert_eq!(declaration.function_declarations[0].name, "echo");
+
+        registry.register(Slow);
+        let declaration = registry.declaration_where(|name| name != "echo").unwrap();
+        assert_eq!(declaration.function_declarations.len(), 1);
+        assert_eq!(declaration.function_declarations[0].name, "slow");
+        assert!(registry.declaration_where(|_| false).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_answers_each_call_by_id() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Echo);
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        registry.dispatch(
+            ToolCall {
+                function_calls: vec![call("a", "echo"), call("b", "missing")],
+            },
+            tx,
+        );
+
+        let mut responses = Vec::new();
+        for _ in 0..2 {
+            match rx.recv().await {
+                Some(WsOutbound::ToolResponse(r)) => responses.extend(r.function_responses),
+                other => panic!("unexpected message: {:?}", other),
+            }
+        }
+        responses.sort_by(|a, b| a.id.cmp(&b.id));
+
+        assert_eq!(responses[0].id, "a");
+        assert_eq!(responses[0].response, json!({ "output": { "x": 1 } }));
+        assert_eq!(responses[1].id, "b");
+        assert!(responses[1].response.get("error").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_aborts_in_flight_call() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Slow);
+        registry.register(Echo);
+
+        let declaration = registry.declaration().unwrap();
+        let slow = declaration
+            .function_declarations
+            .iter()
+            .find(|d| d.name == "slow")
+            .unwrap();
+        assert_eq!(slow.behavior, Some(FunctionBehavior::NonBlocking));
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        registry.dispatch(
+            ToolCall {
+                function_calls: vec![call("s", "slow")],
+            },
+            tx.clone(),
+        );
+        registry.cancel(&["s".to_string()]);
+        assert!(registry.in_flight.lock().unwrap().is_empty());
+
+        // Only the later call is answered
+        registry.dispatch(
+            ToolCall {
+                function_calls: vec![call("e", "echo")],
+            },
+            tx,
+        );
+        match rx.recv().await {
+            Some(WsOutbound::ToolResponse(r)) => {
+                assert_eq!(r.function_responses[0].id, "e");
+                assert_eq!(r.function_responses[0].scheduling, None);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_non_blocking_response_carries_scheduling() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Notify);
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        registry.dispatch(
+            ToolCall {
+                function_calls: vec![call("n", "notify")],
+            },
+            tx,
+        );
+        let tool_response = match rx.recv().await {
+            Some(WsOutbound::ToolResponse(r)) => r,
+            other => panic!("unexpected message: {:?}", other),
+        };
+
+        let wire = serde_json::to_value(ClientMessage::ToolResponse { tool_response }).unwrap();
+        let response = &wire["toolResponse"]["functionResponses"][0];
+        assert_eq!(response["id"], "n");
+        assert_eq!(response["scheduling"], "SILENT");
+    }
+}
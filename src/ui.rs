@@ -16,10 +16,10 @@ pub struct AudioSample {
 /// Conversation entry
 #[derive(Clone, Debug)]
 pub struct ConversationEntry {
-    pub role: String, // "User" or "Gemini"
+    pub role: String, // "User", "Gemini", "Code", "Sources" or "Escalation"
     pub text: String,
     pub timestamp: Instant,
-    pub is_streaming: bool, // Whether this entry is still being updated
+    pub is_streaming: bool, // Whether this entry is still being updated; empty text withdraws it
 }
 
 pub struct UiState {
@@ -59,6 +59,20 @@ pub struct UiState {
     /// Latency tracking
     pub pending_turns_count: usize,
     pub avg_latency_ms: f32,
+    /// Token usage of the latest response and of the whole session
+    pub turn_tokens: u64,
+    pub session_tokens: u64,
+    /// Messages waiting to be sent and media shed because the uplink is slow
+    pub send_queue_depth: usize,
+    pub media_dropped: u64,
+    /// Turns the model answered and turns it stayed silent
+    pub answered_turns: u32,
+    pub silent_turns: u32,
+    /// Prompt profiles to cycle through and the one in use
+    pub profiles: Vec<String>,
+    pub active_profile: String,
+    /// Asks the app to switch to the named profile
+    pub profile_requests: Option<tokio::sync::mpsc::UnboundedSender<String>>,
 }
 
 pub struct UiApp {
@@ -94,6 +108,15 @@ impl UiApp {
             typewriter_last_update: Instant::now(),
             pending_turns_count: 0,
             avg_latency_ms: 0.0,
+            turn_tokens: 0,
+            session_tokens: 0,
+            send_queue_depth: 0,
+            media_dropped: 0,
+            answered_turns: 0,
+            silent_turns: 0,
+            profiles: Vec::new(),
+            active_profile: String::new(),
+            profile_requests: None,
         };
         
         // Initialize with some flat audio samples
@@ -216,6 +239,7 @@ impl UiApp {
             // Process keyboard shortcuts
             let mut toggle_collapse = false;
             let mut toggle_mute = false;
+            let mut next_profile = false;
             
             // First check if window has focus
             if !backend.window.is_focused() {
@@ -250,6 +274,12 @@ impl UiApp {
                             toggle_mute = true;
                         }
                     }
+                    egui::Event::Key { key: egui::Key::P, pressed: true, modifiers, .. } => {
+                        if modifiers.shift && modifiers.ctrl {
+                            eprintln!("Next profile triggered!");
+                            next_profile = true;
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -265,6 +295,16 @@ impl UiApp {
                     state_guard.is_muted = !state_guard.is_muted;
                 }
             }
+            if next_profile {
+                // The app reconnects with the new profile and updates `active_profile`
+                let state_guard = state.lock().unwrap();
+                let profiles = &state_guard.profiles;
+                if let (Some(requests), false) = (&state_guard.profile_requests, profiles.is_empty()) {
+                    let current = profiles.iter().position(|name| *name == state_guard.active_profile);
+                    let next = current.map_or(0, |i| (i + 1) % profiles.len());
+                    let _ = requests.send(profiles[next].clone());
+                }
+            }
 
 
             // Animate height changes
@@ -453,14 +493,21 @@ impl UiApp {
                                             for entry in &state_guard.conversation_history {
                                                 ui.group(|ui| {
                                                     ui.horizontal(|ui| {
-                                                        if entry.role == "User" {
-                                                            ui.label(RichText::new("👤").size(14.0));
-                                                        } else {
-                                                            ui.label(RichText::new("🤖").size(14.0));
-                                                        }
+                                                        let icon = match entry.role.as_str() {
+                                                            "User" => "👤",
+                                                            "Code" => "💻",
+                                                            "Sources" => "🔗",
+                                                            "Escalation" => "🧠",
+                                                            _ => "🤖",
+                                                        };
+                                                        ui.label(RichText::new(icon).size(14.0));
                                                         ui.add_space(8.0);
                                                     });
-                                                    render_text_with_code_blocks(ui, &entry.text);
+                                                    if entry.role == "Sources" {
+                                                        render_sources(ui, &entry.text);
+                                                    } else {
+                                                        render_text_with_code_blocks(ui, &entry.text);
+                                                    }
                                                 });
                                                 ui.add_space(8.0);
                                             }
@@ -481,12 +528,19 @@ impl UiApp {
                                         if state_guard.show_debug {
                                             ui.label(
                                                 RichText::new(format!(
-                                                    "Segments: {} | Frames Sent: {} | FPS: {:.0} | Pending Turns: {} | Avg Latency: {:.0}ms",
+                                                    "Profile: {} | Segments: {} | Frames Sent: {} | FPS: {:.0} | Pending Turns: {} | Turns: {} answered / {} silent | Avg Latency: {:.0}ms | Tokens: {} turn / {} session | Send Queue: {} ({} dropped)",
+                                                    state_guard.active_profile,
                                                     state_guard.segments_processed,
                                                     state_guard.frames_sent,
                                                     fps,
                                                     state_guard.pending_turns_count,
-                                                    state_guard.avg_latency_ms
+                                                    state_guard.answered_turns,
+                                                    state_guard.silent_turns,
+                                                    state_guard.avg_latency_ms,
+                                                    state_guard.turn_tokens,
+                                                    state_guard.session_tokens,
+                                                    state_guard.send_queue_depth,
+                                                    state_guard.media_dropped
                                                 ))
                                                 .size(11.0)
                                                 .color(Color32::from_gray(120))
@@ -573,6 +627,21 @@ fn render_text_with_code_blocks(ui: &mut egui::Ui, text: &str) {
     }
 }
 
+/// Render grounding sources as links; lines ending in a URL become hyperlinks
+fn render_sources(ui: &mut egui::Ui, text: &str) {
+    for line in text.lines() {
+        let (title, url) = match line.rsplit_once(' ') {
+            Some((title, url)) if url.starts_with("http") => (title, url),
+            _ if line.starts_with("http") => (line, line),
+            _ => {
+                ui.label(RichText::new(line).size(13.0).color(Color32::from_gray(160)).italics());
+                continue;
+            }
+        };
+        ui.hyperlink_to(RichText::new(title).size(14.0), url);
+    }
+}
+
 /// Draw horizontal audio visualization
 fn draw_horizontal_audio_viz(ui: &mut egui::Ui, samples: &VecDeque<AudioSample>, is_speaking: bool) {
     let rect = ui.available_rect_before_wrap();
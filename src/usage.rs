@@ -0,0 +1,152 @@
+//! Token accounting for a Live session
+//!
+//! Every `usageMetadata` report from the server is counted once towards the
+//! session totals. An optional budget is checked against the session total
+//! and reports the configured action the first time it is exceeded.
+
+use crate::gemini::{ModalityTokenCount, UsageMetadata};
+use std::collections::BTreeMap;
+use tracing::{info, warn};
+
+/// Token counts broken down by direction and modality
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenCounts {
+    pub prompt: u64,
+    pub response: u64,
+    pub total: u64,
+    /// Prompt tokens per modality, e.g. "AUDIO" -> 1200
+    pub prompt_by_modality: BTreeMap<String, u64>,
+    /// Response tokens per modality
+    pub response_by_modality: BTreeMap<String, u64>,
+}
+
+impl TokenCounts {
+    fn add(&mut self, usage: &UsageMetadata) {
+        self.prompt += usage.prompt_token_count;
+        self.response += usage.response_token_count;
+        self.total += usage.total_token_count;
+        add_details(&mut self.prompt_by_modality, &usage.prompt_tokens_details);
+        add_details(&mut self.response_by_modality, &usage.response_tokens_details);
+    }
+}
+
+impl From<&UsageMetadata> for TokenCounts {
+    fn from(usage: &UsageMetadata) -> Self {
+        let mut counts = Self::default();
+        counts.add(usage);
+        counts
+    }
+}
+
+fn add_details(totals: &mut BTreeMap<String, u64>, details: &[ModalityTokenCount]) {
+    for detail in details {
+        *totals.entry(detail.modality.clone()).or_default() += detail.token_count;
+    }
+}
+
+/// What to do once the session has used up its token budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetAction {
+    /// Log a warning and keep going
+    Warn,
+    /// Stop sending screen frames; audio keeps flowing
+    PauseVideo,
+}
+
+/// Upper bound on total tokens for a session
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBudget {
+    pub max_total_tokens: u64,
+    pub action: BudgetAction,
+}
+
+/// Running token totals for the session
+#[derive(Debug, Default)]
+pub struct UsageTracker {
+    session: TokenCounts,
+    budget: Option<TokenBudget>,
+    budget_exceeded: bool,
+}
+
+impl UsageTracker {
+    pub fn new(budget: Option<TokenBudget>) -> Self {
+        Self {
+            budget,
+            ..Default::default()
+        }
+    }
+
+    /// Count a usage report, returning the budget action if this report
+    /// pushed the session over its budget.
+    pub fn record(&mut self, usage: &UsageMetadata) -> Option<BudgetAction> {
+        self.session.add(usage);
+
+        info!(
+            "📊 Tokens this turn: {} (prompt {}, response {}) | session: {}",
+            usage.total_token_count,
+            usage.prompt_token_count,
+            usage.response_token_count,
+            self.session.total
+        );
+
+        let budget = self.budget?;
+        if self.budget_exceeded || self.session.total <= budget.max_total_tokens {
+            return None;
+        }
+
+        self.budget_exceeded = true;
+        warn!(
+            "Token budget exceeded: {} of {} tokens used, action: {:?}",
+            self.session.total, budget.max_total_tokens, budget.action
+        );
+        Some(budget.action)
+    }
+
+    /// Totals for the whole session so far
+    pub fn session(&self) -> &TokenCounts {
+        &self.session
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt: u64, response: u64) -> UsageMetadata {
+        UsageMetadata {
+            prompt_token_count: prompt,
+            response_token_count: response,
+            total_token_count: prompt + response,
+            prompt_tokens_details: vec![ModalityTokenCount {
+                modality: "AUDIO".to_string(),
+                token_count: prompt,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_session_totals_by_modality() {
+        let mut tracker = UsageTracker::new(None);
+        tracker.record(&usage(100, 10));
+        tracker.record(&usage(200, 20));
+
+        let session = tracker.session();
+        assert_eq!(session.prompt, 300);
+        assert_eq!(session.response, 30);
+        assert_eq!(session.total, 330);
+        assert_eq!(session.prompt_by_modality["AUDIO"], 300);
+        assert!(session.response_by_modality.is_empty());
+    }
+
+    #[test]
+    fn test_budget_reports_action_once() {
+        let mut tracker = UsageTracker::new(Some(TokenBudget {
+            max_total_tokens: 250,
+            action: BudgetAction::PauseVideo,
+        }));
+        assert_eq!(tracker.record(&usage(100, 10)), None);
+        assert_eq!(tracker.record(&usage(200, 20)), Some(BudgetAction::PauseVideo));
+        assert_eq!(tracker.record(&usage(200, 20)), None);
+    }
+}
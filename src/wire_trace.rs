@@ -0,0 +1,345 @@
+//! JSONL trace of the frames exchanged with the Live API
+//!
+//! Every frame sent or received is written as one line, e.g.
+//!
+//! ```json
+//! {"t_ms":1520.375,"conn":2,"dir":"send","kind":"text","msg":{"realtimeInput":{"audio":{"mimeType":"audio/pcm;rate=16000","data":{"bytes":640,"fnv1a":"9c1e5f0b2a7d4e31"}}}}}
+//! ```
+//!
+//! `t_ms` is monotonic from the start of the trace and `conn` numbers the
+//! connections of the run. Base64 payloads, the `data` of any object that
+//! also has a `mimeType`, are replaced by their decoded size and FNV-1a hash,
+//! which keeps traces small and diffable. With a payload directory the
+//! decoded bytes are also written there, named after their hash.
+//!
+//! Callers only timestamp the frame and hand it over; parsing, hashing and
+//! file I/O happen on a dedicated writer thread so tracing does not slow the
+//! realtime path.
+
+use crate::auth::redact_url;
+use anyhow::{Context, Result};
+use base64::engine::general_purpose;
+use base64::Engine;
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::warn;
+
+/// Hands trace records to the writer thread; shared by all connections of a client
+#[derive(Clone)]
+pub struct WireTracer {
+    records: mpsc::Sender<Record>,
+    start: Instant,
+    connections: Arc<AtomicU64>,
+}
+
+/// Work for the writer thread
+enum Record {
+    /// A finished line
+    Line(Map<String, Value>),
+    /// A frame, formatted on the writer thread after the given fields
+    Frame(Map<String, Value>, Message),
+    /// Acknowledge once everything before has been written
+    Flush(mpsc::Sender<()>),
+}
+
+impl WireTracer {
+    /// Start a trace at `path`, dumping payloads into `payload_dir` if given.
+    pub fn create(path: &Path, payload_dir: Option<PathBuf>) -> Result<Self> {
+        if let Some(dir) = &payload_dir {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create payload directory {}", dir.display()))?;
+        }
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create wire trace {}", path.display()))?;
+        let (records, rx) = mpsc::channel();
+        let mut writer = TraceWriter {
+            writer: LineWriter::new(file),
+            failed: false,
+            payload_dir,
+        };
+        std::thread::Builder::new()
+            .name("wire-trace".to_string())
+            .spawn(move || writer.run(rx))
+            .context("Failed to start wire trace writer")?;
+
+        let tracer = Self {
+            records,
+            start: Instant::now(),
+            connections: Arc::new(AtomicU64::new(0)),
+        };
+        // Anchor the monotonic timestamps to the wall clock
+        tracer.send(Record::Line(Map::from_iter([
+            ("t_ms".to_string(), json!(0.0)),
+            ("kind".to_string(), json!("start")),
+            ("time".to_string(), json!(chrono::Local::now().to_rfc3339())),
+        ])));
+        Ok(tracer)
+    }
+
+    /// Trace a new connection to `url`.
+    pub fn connection(&self, url: &str) -> ConnectionTrace {
+        let conn = self.connections.fetch_add(1, Ordering::Relaxed) + 1;
+        let trace = ConnectionTrace {
+            tracer: self.clone(),
+            conn,
+        };
+        let mut line = trace.header("connect");
+        line.insert("url".to_string(), json!(redact_url(url)));
+        self.send(Record::Line(line));
+        trace
+    }
+
+    /// Wait until everything recorded so far is written.
+    pub fn flush(&self) {
+        let (done_tx, done_rx) = mpsc::channel();
+        self.send(Record::Flush(done_tx));
+        let _ = done_rx.recv();
+    }
+
+    fn elapsed_ms(&self) -> f64 {
+        (self.start.elapsed().as_secs_f64() * 1_000_000.0).round() / 1000.0
+    }
+
+    fn send(&self, record: Record) {
+        // The writer only goes away if it panicked, the trace just stops then
+        let _ = self.records.send(record);
+    }
+}
+
+/// Records the frames of one connection
+#[derive(Clone)]
+pub struct ConnectionTrace {
+    tracer: WireTracer,
+    conn: u64,
+}
+
+impl ConnectionTrace {
+    pub fn sent(&self, message: &Message) {
+        self.frame("send", message);
+    }
+
+    pub fn received(&self, message: &Message) {
+        self.frame("recv", message);
+    }
+
+    fn frame(&self, dir: &str, message: &Message) {
+        if let Message::Frame(_) = message {
+            return;
+        }
+        let mut line = self.header(frame_kind(message));
+        line.insert("dir".to_string(), json!(dir));
+        // Frame payloads are reference counted, so the clone is cheap
+        self.tracer.send(Record::Frame(line, message.clone()));
+    }
+
+    /// The fields every line of this connection starts with
+    fn header(&self, kind: &str) -> Map<String, Value> {
+        Map::from_iter([
+            ("t_ms".to_string(), json!(self.tracer.elapsed_ms())),
+            ("conn".to_string(), json!(self.conn)),
+            ("kind".to_string(), json!(kind)),
+        ])
+    }
+}
+
+fn frame_kind(message: &Message) -> &'static str {
+    match message {
+        Message::Text(_) => "text",
+        Message::Binary(_) => "binary",
+        Message::Ping(_) => "ping",
+        Message::Pong(_) => "pong",
+        Message::Close(_) => "close",
+        Message::Frame(_) => "frame",
+    }
+}
+
+/// Owns the trace file on the writer thread
+struct TraceWriter {
+    writer: LineWriter<File>,
+    /// Set after the first failed write; the trace stops there
+    failed: bool,
+    payload_dir: Option<PathBuf>,
+}
+
+impl TraceWriter {
+    fn run(&mut self, records: mpsc::Receiver<Record>) {
+        // Ends when every tracer and connection trace is dropped
+        for record in records {
+            match record {
+                Record::Line(line) => self.write(line),
+                Record::Frame(mut line, message) => {
+                    self.describe(&mut line, &message);
+                    self.write(line);
+                }
+                Record::Flush(done) => {
+                    let _ = done.send(());
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, line: Map<String, Value>) {
+        if self.failed {
+            return;
+        }
+        if let Err(e) = writeln!(self.writer, "{}", Value::Object(line)) {
+            warn!("Wire trace stopped, could not write: {}", e);
+            self.failed = true;
+        }
+    }
+
+    /// Add the contents of `message` to its line.
+    fn describe(&self, line: &mut Map<String, Value>, message: &Message) {
+        match message {
+            Message::Text(text) => {
+                line.insert("msg".to_string(), self.parse(text.as_str()));
+            }
+            Message::Binary(bytes) => {
+                // The Live API sends JSON in binary frames too
+                match std::str::from_utf8(bytes) {
+                    Ok(text) => line.insert("msg".to_string(), self.parse(text)),
+                    Err(_) => line.insert("bytes".to_string(), json!(bytes.len())),
+                };
+            }
+            Message::Ping(bytes) | Message::Pong(bytes) => {
+                line.insert("bytes".to_string(), json!(bytes.len()));
+            }
+            Message::Close(frame) => {
+                if let Some(frame) = frame {
+                    line.insert("code".to_string(), json!(u16::from(frame.code)));
+                    line.insert("reason".to_string(), json!(frame.reason.as_str()));
+                }
+            }
+            Message::Frame(_) => {}
+        }
+    }
+
+    /// The message as JSON with payloads redacted, or the raw text if it is not JSON
+    fn parse(&self, text: &str) -> Value {
+        match serde_json::from_str::<Value>(text) {
+            Ok(mut value) => {
+                self.redact_payloads(&mut value);
+                value
+            }
+            Err(_) => json!({ "raw": text }),
+        }
+    }
+
+    /// Replace base64 payloads below `value` with their size and hash.
+    fn redact_payloads(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                let mime_type = object.get("mimeType").and_then(Value::as_str).map(str::to_string);
+                if let (Some(mime_type), Some(Value::String(data))) = (mime_type, object.get("data")) {
+                    let summary = self.summarize_payload(data, &mime_type);
+                    object.insert("data".to_string(), summary);
+                }
+                for child in object.values_mut() {
+                    self.redact_payloads(child);
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_payloads(item)),
+            _ => {}
+        }
+    }
+
+    fn summarize_payload(&self, data: &str, mime_type: &str) -> Value {
+        let bytes = general_purpose::STANDARD
+            .decode(data)
+            .unwrap_or_else(|_| data.as_bytes().to_vec());
+        let hash = format!("{:016x}", fnv1a(&bytes));
+        let mut summary = json!({ "bytes": bytes.len(), "fnv1a": hash });
+
+        if let Some(dir) = &self.payload_dir {
+            let name = format!("{}.{}", hash, extension(mime_type));
+            let path = dir.join(&name);
+            // Identical payloads, such as unchanged frames, are stored once
+            if !path.exists() {
+                if let Err(e) = std::fs::write(&path, &bytes) {
+                    warn!("Could not write payload {}: {}", path.display(), e);
+                }
+            }
+            summary["file"] = json!(name);
+        }
+        summary
+    }
+}
+
+/// 64-bit FNV-1a, stable across runs and platforms
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// File extension for a payload, e.g. `pcm` for `audio/pcm;rate=16000`
+fn extension(mime_type: &str) -> String {
+    let subtype = mime_type.split(';').next().unwrap_or("").rsplit('/').next().unwrap_or("");
+    match subtype {
+        "jpeg" => "jpg".to_string(),
+        subtype if !subtype.is_empty() && subtype.chars().all(|c| c.is_ascii_alphanumeric()) => {
+            subtype.to_string()
+        }
+        _ => "bin".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_redacts_payloads() {
+        let dir = std::env::temp_dir().join(format!("rholive-trace-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("trace.jsonl");
+        let tracer = WireTracer::create(&path, Some(dir.join("payloads"))).unwrap();
+
+        let trace = tracer.connection("wss://example.com/ws?key=secret");
+        let audio = general_purpose::STANDARD.encode([1u8, 2, 3, 4]);
+        trace.sent(&Message::Text(
+            json!({ "realtimeInput": { "audio": { "mimeType": "audio/pcm;rate=16000", "data": audio } } })
+                .to_string()
+                .into(),
+        ));
+        trace.received(&Message::Text(r#"{"setupComplete":{}}"#.into()));
+        trace.received(&Message::Pong(Vec::new().into()));
+        tracer.flush();
+
+        let lines: Vec<Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let payload = dir.join("payloads").join(format!("{:016x}.pcm", fnv1a(&[1, 2, 3, 4])));
+        let dumped = std::fs::read(&payload).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(lines[0]["kind"], "start");
+        assert_eq!(lines[1]["kind"], "connect");
+        assert!(!lines[1]["url"].as_str().unwrap().contains("secret"));
+        assert_eq!(lines[2]["conn"], 1);
+        assert_eq!(lines[2]["dir"], "send");
+        let data = &lines[2]["msg"]["realtimeInput"]["audio"]["data"];
+        assert_eq!(data["bytes"], 4);
+        assert_eq!(data["file"], format!("{:016x}.pcm", fnv1a(&[1, 2, 3, 4])));
+        assert_eq!(lines[3]["msg"], json!({ "setupComplete": {} }));
+        assert_eq!(lines[4]["kind"], "pong");
+        assert!(lines[4]["t_ms"].as_f64().unwrap() >= lines[2]["t_ms"].as_f64().unwrap());
+        assert_eq!(dumped, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_extension() {
+        assert_eq!(extension("audio/pcm;rate=16000"), "pcm");
+        assert_eq!(extension("image/jpeg"), "jpg");
+        assert_eq!(extension("application/x-thing"), "bin");
+    }
+}